@@ -0,0 +1,126 @@
+//! Timings of `island::run` on large generated maps
+//!
+//! `cargo bench` prints the best of several runs for every terrain, and the time spent in each
+//! step on the largest map. Only the standard library is used, so the numbers are rough: compare
+//! runs made on the same machine.
+
+use garm_playground::libs::island::{
+    self, terrain, Connectivity, DynamicMap, FloodFill, Generator, Labeler, Map, Options, Parallel,
+    Random, TERRAIN_NAMES,
+};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+const SIZES: [usize; 2] = [500, 2000];
+
+/// Best duration of `RUNS` calls of `f`
+fn best_of<T, F: FnMut() -> T>(mut f: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn report(name: &str, cells: usize, duration: Duration) {
+    println!(
+        "{:<28} {:>10.2} ms {:>10.1} Mcells/s",
+        name,
+        duration.as_secs_f64() * 1e3,
+        cells as f64 / duration.as_secs_f64() / 1e6
+    );
+}
+
+fn main() {
+    for size in SIZES {
+        let cells = size * size;
+        println!("{}x{} maps", size, size);
+
+        for name in TERRAIN_NAMES {
+            let terrain = terrain(name).expect("[FATAL] unknown terrain");
+            let raw_map = Generator::new(size, size)
+                .seed(1)
+                .generate(&terrain)
+                .to_string();
+
+            report(
+                &format!("  run ({})", name),
+                cells,
+                best_of(|| island::run(&raw_map)),
+            );
+        }
+    }
+
+    let size = SIZES[SIZES.len() - 1];
+    let cells = size * size;
+    let raw_map = Generator::new(size, size)
+        .seed(1)
+        .generate(&terrain("uniform").expect("[FATAL] unknown terrain"))
+        .to_string();
+    let options = Options::default();
+    println!("steps, {}x{} uniform map", size, size);
+
+    report(
+        "  parse",
+        cells,
+        best_of(|| raw_map.parse::<Map>().unwrap()),
+    );
+    report(
+        "  parse + label",
+        cells,
+        best_of(|| island::label_map(raw_map.parse().unwrap(), &options)),
+    );
+    let map = island::label_map(raw_map.parse().unwrap(), &options);
+    report("  render", cells, best_of(|| map.to_string()));
+    report(
+        "  get (every cell)",
+        cells,
+        best_of(|| map.cursor().iter().filter(|c| c.read().is_marked()).count()),
+    );
+
+    let unlabelled: Map = raw_map.parse().unwrap();
+    let connectivity = Connectivity::four();
+    println!("labelers, {}x{} uniform map", size, size);
+
+    report(
+        "  flood-fill",
+        cells,
+        best_of(|| FloodFill.label(unlabelled.clone(), &connectivity)),
+    );
+    for threads in [1, 2, 4, 8] {
+        let parallel = Parallel::new(threads);
+        report(
+            &format!("  parallel ({} threads)", threads),
+            cells,
+            best_of(|| parallel.label(unlabelled.clone(), &connectivity)),
+        );
+    }
+
+    let edits = 1000;
+    let mut random = Random::new(1);
+    let coords: Vec<(usize, usize)> = (0..edits)
+        .map(|_| (random.below(size), random.below(size)))
+        .collect();
+    println!("{} edits, {}x{} uniform map", edits, size, size);
+
+    report(
+        "  dynamic map",
+        cells,
+        best_of(|| DynamicMap::new(raw_map.parse().unwrap(), options.connectivity.clone())),
+    );
+    let mut dynamic_map = DynamicMap::new(raw_map.parse().unwrap(), options.connectivity.clone());
+    report(
+        "  toggle",
+        edits,
+        best_of(|| {
+            for &coord in &coords {
+                dynamic_map.toggle(coord);
+            }
+            dynamic_map.island_count()
+        }),
+    );
+}
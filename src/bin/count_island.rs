@@ -1,22 +1,1036 @@
-use garm_playground::libs::island;
+use garm_playground::libs::island::{self, Cell, Label, LabelEncoder, Map, StreamError};
 use std::env;
-use std::fs;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::process;
+use std::str::FromStr;
 
-/// # Usage: `cargo run <filepath>`
+/// # Usage: `cargo run [<command>] [options] <file>...`
 ///
 /// You may find usable maps in the `misc/count_island` directory of the project.
 ///
-/// usage: `cargo run misc/count_island/complex_map.txt`
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    const USAGE: &str = "Usage: cargo run <filepath>";
+/// usage: `cargo run label misc/count_island/complex_map.txt`
+///
+/// - `label` (the default command) prints the labelled maps
+/// - `count` prints the number of islands, text maps being read line by line once
+/// - `stats` prints a table of per island statistics
+/// - `verify` checks already labelled maps, every problem is printed
+/// - `regions` prints the number of regions of every character, e.g. of a terrain map
+/// - `sweep` prints the number of islands of a heightmap at every water level
+/// - `generate` prints a random map
+///
+/// Every command reads the files given on the command line, `-` being the standard input, and
+/// writes to the standard output or to the file given with `-o`. `cargo run <command> --help`
+/// lists the options of a command.
+///
+/// Files ending with `.pbm`, `.pgm` or `.pnm` are read as Netpbm images, black bitmap pixels and
+/// greymap samples at or above `--threshold` (half the maximum value by default) being earth.
+///
+/// Parse failures are printed as diagnostics pointing at the offending lines, coloured when
+/// printed to a terminal. `--plain` (or the `NO_COLOR` environment variable) disables colours.
+///
+/// The exit code is `0` on success, see [`exit`] for the others. When several files are given,
+/// every file is processed and the exit code is the one of the first failure.
+fn main() {
+    let code = match parse_args(env::args().skip(1)) {
+        Ok(Invocation::Help(help)) => {
+            print!("{}", help);
+            0
+        }
+        Ok(Invocation::Run(args)) => run(&args),
+        Err(failure) => failure.report(),
+    };
+
+    process::exit(code);
+}
+
+/// Exit codes of count_island
+mod exit {
+    /// An input is not a valid map, or `verify` found a problem
+    pub const PARSE: i32 = 1;
+    /// Unknown command or option, missing or invalid option value
+    pub const USAGE: i32 = 2;
+    /// An input could not be read or the output written
+    pub const IO: i32 = 3;
+    /// A label cannot be rendered by `--encoding`, e.g. more than 36 islands in `base36`
+    pub const OVERFLOW: i32 = 4;
+}
+
+const USAGE: &str = "\
+Usage: count_island [<command>] [options] <file>...
+
+Label the islands of maps, `-` reads a map from the standard input.
+
+Commands:
+    label       print the labelled maps (default)
+    count       print the number of islands
+    stats       print per island statistics
+    verify      check already labelled maps
+    regions     print the regions of every character
+    sweep       print the islands of heightmaps at every water level
+    generate    print a random map
+    help        print the help of a command
+
+Run `count_island <command> --help` for the options of a command.
+
+Exit codes: 0 success, 1 invalid map, 2 usage error, 3 I/O error, 4 label overflow
+";
+
+const MAP_OPTIONS: &str = "\
+Map options:
+    --connectivity <4|8|knight|dx:dy,...>   neighbours of a same island (default 4)
+    --ragged <pad|strict|trim>              how lines of different lengths are parsed (default pad)
+    --lenient                               repair invalid maps, errors being printed as warnings
+                                            (kept in the report with --format json)
+    --fail-fast                             stop parsing a map on its first error
+    --max-errors <n>                        stop parsing a map once n errors are found
+    --charset <default|dots|binary>         characters of sea, earth and marks
+    --sea <char>                            sea character, overrides the charset
+    --earth <char>                          earth character, overrides the charset
+    --marks <base36|base62|greek|unicode|none>
+                                            marks, override the charset
+    --threshold <level>                     lowest earth level of .pgm images (default half the
+                                            maximum)
+    --plain                                 do not colour diagnostics
+";
+
+const COMMON_OPTIONS: &str = "\
+Options:
+    -o <file>                               write to <file> instead of the standard output
+    -h, --help                              print this help
+";
+
+const LABEL_USAGE: &str = "\
+Usage: count_island label [options] <file>...
+
+Print the labelled maps, preceded by their name when there are several.
+
+Label options:
+    --encoding <base36|base62|greek|unicode|csv>
+                                            how island labels are rendered (default the marks,
+                                            csv without marks)
+    --lakes <encoding>                      render the sea enclosed by islands with <encoding>
+    --labeler <flood-fill|union-find|parallel>
+                                            connected-components engine (default flood-fill)
+    --threads <n>                           threads of the parallel labeler (default one per
+                                            core)
+    --preserve-marks                        keep the labels already on the maps
+    --format <text|json>                    print a JSON report of every map, one per line
+    --ppm <plain|raw>                       print colour images, one colour per island
+    --stream                                read the files line by line (twice), for maps larger
+                                            than memory. The standard input cannot be streamed,
+                                            nor can --lenient, --labeler, --preserve-marks,
+                                            --lakes, --format json and --ppm be used
+";
+
+const COUNT_USAGE: &str = "\
+Usage: count_island count [options] <file>...
+
+Print the number of islands, preceded by the name of the map when there are several. Text maps
+are read line by line once, unless --lenient or --labeler is given.
+
+Count options:
+    --labeler <flood-fill|union-find|parallel>
+                                            connected-components engine (default flood-fill)
+    --threads <n>                           threads of the parallel labeler (default one per
+                                            core)
+";
+
+const STATS_USAGE: &str = "\
+Usage: count_island stats [options] <file>...
+
+Print a table of per island statistics, preceded by the name of the map when there are several.
+
+Stats options:
+    --encoding <base36|base62|greek|unicode|csv>
+                                            how labels are rendered in the JSON report
+    --labeler <flood-fill|union-find|parallel>
+                                            connected-components engine (default flood-fill)
+    --threads <n>                           threads of the parallel labeler (default one per
+                                            core)
+    --preserve-marks                        keep the labels already on the maps
+    --format <text|json>                    print a JSON report of every map, one per line
+";
+
+const VERIFY_USAGE: &str = "\
+Usage: count_island verify [options] <file>...
+
+Check the labels of already labelled maps, every problem is printed.
+
+Verify options:
+    --labeler <flood-fill|union-find|parallel>
+                                            connected-components engine (default flood-fill)
+    --threads <n>                           threads of the parallel labeler (default one per
+                                            core)
+";
+
+const REGIONS_USAGE: &str = "\
+Usage: count_island regions [options] <file>...
+
+Print the number of regions and cells of every character, preceded by the name of the map when
+there are several. Every character is a class, e.g. forest, desert and water on a terrain map,
+and neighbouring cells of the same class form a region.
+
+Regions options:
+    --connectivity <4|8|knight|dx:dy,...>   neighbours of a same region (default 4)
+    --ragged <pad|strict|trim>              how lines of different lengths are parsed, the
+                                            padding of short lines being in no class (default pad)
+    --lenient                               accept empty and ragged lines, errors being printed
+                                            as warnings
+    --fail-fast                             stop parsing a map on its first error
+    --max-errors <n>                        stop parsing a map once n errors are found
+    --plain                                 do not colour diagnostics
+";
+
+const SWEEP_USAGE: &str = "\
+Usage: count_island sweep [options] <file>...
+
+Print the number of islands of heightmaps at every water level, preceded by the name of the map
+when there are several. Every cell is a digit, its height, and land is every cell above the
+water level. Splits are the islands of the level below split by the water, sunk the ones under
+water.
+
+Sweep options:
+    --level <n>                             also print the islands labelled at the level <n>
+    --encoding <base36|base62|greek|unicode|csv>
+                                            how island labels are rendered (default the marks,
+                                            csv without marks)
+    --labeler <flood-fill|union-find|parallel>
+                                            connected-components engine (default flood-fill)
+    --threads <n>                           threads of the parallel labeler (default one per
+                                            core)
+    --connectivity <4|8|knight|dx:dy,...>   neighbours of a same island (default 4)
+    --ragged <pad|strict|trim>              how lines of different lengths are parsed, short
+                                            lines being padded with 0 (default pad)
+    --lenient                               repair invalid maps, other characters than digits
+                                            being 0 and errors being printed as warnings
+    --fail-fast                             stop parsing a map on its first error
+    --max-errors <n>                        stop parsing a map once n errors are found
+    --plain                                 do not colour diagnostics
+";
+
+const GENERATE_USAGE: &str = "\
+Usage: count_island generate [options]
+
+Print a random map, the same seed always giving the same map.
+
+Generate options:
+    --width <n>                             number of cells per line (default 80)
+    --height <n>                            number of lines (default 24)
+    --seed <n>                              seed of the random generator (default 0)
+    --terrain <uniform|noise|caves>         random cells, smoothed heights or cellular-automaton
+                                            caves (default uniform)
+    --density <0..1>                        share of earth cells (default 0.5, 0.45 for caves)
+    --scale <n>                             size in cells of the largest noise features
+                                            (default 16)
+    --octaves <n>                           number of noise layers, each twice as detailed
+                                            (default 3)
+    --steps <n>                             rounds of cave smoothing (default 4)
+    --charset <default|dots|binary>         characters of sea and earth
+    --sea <char>                            sea character, overrides the charset
+    --earth <char>                          earth character, overrides the charset
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Label,
+    Count,
+    Stats,
+    Verify,
+    Regions,
+    Sweep,
+    Generate,
+}
+
+impl FromStr for Command {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "label" => Ok(Command::Label),
+            "count" => Ok(Command::Count),
+            "stats" => Ok(Command::Stats),
+            "verify" => Ok(Command::Verify),
+            "regions" => Ok(Command::Regions),
+            "sweep" => Ok(Command::Sweep),
+            "generate" => Ok(Command::Generate),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Command {
+    /// Usage line and options specific to the command
+    fn usage(self) -> &'static str {
+        match self {
+            Command::Label => LABEL_USAGE,
+            Command::Count => COUNT_USAGE,
+            Command::Stats => STATS_USAGE,
+            Command::Verify => VERIFY_USAGE,
+            Command::Regions => REGIONS_USAGE,
+            Command::Sweep => SWEEP_USAGE,
+            Command::Generate => GENERATE_USAGE,
+        }
+    }
+
+    /// Full help of the command
+    fn help(self) -> String {
+        match self {
+            Command::Generate | Command::Regions | Command::Sweep => {
+                format!("{}\n{}", self.usage(), COMMON_OPTIONS)
+            }
+            _ => format!("{}\n{}\n{}", self.usage(), MAP_OPTIONS, COMMON_OPTIONS),
+        }
+    }
+}
+
+/*
+ * Arguments
+ */
+
+enum Invocation {
+    Run(Box<Args>),
+    Help(String),
+}
+
+/// Parameters of `generate`, unset ones being the defaults of the terrain
+struct GenerateArgs {
+    width: usize,
+    height: usize,
+    seed: u64,
+    terrain: island::Terrain,
+    density: Option<f64>,
+    scale: Option<usize>,
+    octaves: Option<usize>,
+    steps: Option<usize>,
+}
+
+impl Default for GenerateArgs {
+    fn default() -> Self {
+        GenerateArgs {
+            width: 80,
+            height: 24,
+            seed: 0,
+            terrain: island::terrain("uniform").expect("[FATAL] unknown default terrain"),
+            density: None,
+            scale: None,
+            octaves: None,
+            steps: None,
+        }
+    }
+}
+
+impl GenerateArgs {
+    /// The terrain with the parameters given on the command line
+    fn terrain(&self) -> island::Terrain {
+        let mut terrain = self.terrain;
+
+        match &mut terrain {
+            island::Terrain::Uniform { density } => {
+                *density = self.density.unwrap_or(*density);
+            }
+            island::Terrain::ValueNoise {
+                density,
+                scale,
+                octaves,
+            } => {
+                *density = self.density.unwrap_or(*density);
+                *scale = self.scale.unwrap_or(*scale);
+                *octaves = self.octaves.unwrap_or(*octaves);
+            }
+            island::Terrain::Caves { density, steps } => {
+                *density = self.density.unwrap_or(*density);
+                *steps = self.steps.unwrap_or(*steps);
+            }
+        }
+
+        terrain
+    }
+}
+
+struct Args {
+    command: Command,
+    options: island::Options,
+    inputs: Vec<String>,
+    output: Option<String>,
+    format: island::MapFormat,
+    lenient: bool,
+    colour: bool,
+    json: bool,
+    stream: bool,
+    /// `--labeler` was given
+    labeler: bool,
+    threshold: Option<u16>,
+    ppm: Option<island::NetpbmEncoding>,
+    level: Option<u8>,
+    generate: GenerateArgs,
+}
+
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Invocation, Failure> {
+    use Command::*;
+    let mut args = args.peekable();
+
+    if args.next_if(|arg| arg == "-h" || arg == "--help").is_some() {
+        return Ok(Invocation::Help(USAGE.to_string()));
+    }
+    if args.next_if_eq("help").is_some() {
+        return Ok(Invocation::Help(match args.next() {
+            Some(name) => name
+                .parse::<Command>()
+                .map_err(|_| Failure::usage(None, format!("unknown command '{}'", name)))?
+                .help(),
+            None => USAGE.to_string(),
+        }));
+    }
+
+    let command = match args.peek().and_then(|arg| arg.parse().ok()) {
+        Some(command) => {
+            args.next();
+            command
+        }
+        None => Label,
+    };
+    let reads_maps = command != Generate;
+    // regions reads any character and sweep digits, not sea, earth and marks
+    let reads_cells = reads_maps && !matches!(command, Regions | Sweep);
+    let has_charset = !matches!(command, Regions | Sweep);
+
+    let mut parsed = Args {
+        command,
+        options: island::Options::default(),
+        inputs: vec![],
+        output: None,
+        format: island::MapFormat::DEFAULT,
+        lenient: false,
+        colour: true,
+        json: false,
+        stream: false,
+        labeler: false,
+        threshold: None,
+        ppm: None,
+        level: None,
+        generate: GenerateArgs::default(),
+    };
+    let options = &mut parsed.options;
+    let mut charset = island::MapFormat::DEFAULT;
+    let (mut sea, mut earth, mut marks) = (None, None, None);
+    let (mut parallel, mut threads) = (false, None);
+    let mut encoding = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| {
+                Failure::usage(Some(command), format!("missing value for '{}'", arg))
+            })
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Invocation::Help(command.help())),
+            "-o" => parsed.output = Some(value()?),
+            "--encoding" if matches!(command, Label | Stats | Sweep) => {
+                options.encoder = named(command, &arg, value()?, island::encoder)?;
+                encoding = true;
+            }
+            "--lakes" if command == Label => {
+                let lake_encoder = named(command, &arg, value()?, island::encoder)?;
+                options.lake_encoder = Some(lake_encoder);
+            }
+            "--labeler" if reads_cells || command == Sweep => {
+                let name = value()?;
+                parallel = name == "parallel";
+                parsed.labeler = true;
+                options.labeler = named(command, &arg, name, island::labeler)?;
+            }
+            "--threads" if reads_cells || command == Sweep => {
+                threads = Some(parse_value(command, &arg, value()?)?);
+            }
+            "--preserve-marks" if matches!(command, Label | Stats) => {
+                options.preserve_marks = true;
+            }
+            "--format" if matches!(command, Label | Stats) => {
+                parsed.json = named(command, &arg, value()?, |name| match name {
+                    "text" => Some(false),
+                    "json" => Some(true),
+                    _ => None,
+                })?;
+            }
+            "--ppm" if command == Label => {
+                let encoding = named(command, &arg, value()?, |name| match name {
+                    "plain" => Some(island::NetpbmEncoding::Plain),
+                    "raw" => Some(island::NetpbmEncoding::Raw),
+                    _ => None,
+                })?;
+                parsed.ppm = Some(encoding);
+            }
+            "--stream" if command == Label => parsed.stream = true,
+            "--connectivity" if reads_maps => {
+                options.connectivity = parse_value(command, &arg, value()?)?;
+            }
+            "--ragged" if reads_maps => {
+                let ragged_lines = named(command, &arg, value()?, |name| match name {
+                    "pad" => Some(island::RaggedLines::Pad),
+                    "strict" => Some(island::RaggedLines::Strict),
+                    "trim" => Some(island::RaggedLines::Trim),
+                    _ => None,
+                })?;
+                options.parse = options.parse.ragged_lines(ragged_lines);
+            }
+            "--lenient" if reads_maps => {
+                options.parse = options.parse.lenient();
+                parsed.lenient = true;
+            }
+            "--fail-fast" if reads_maps => options.parse = options.parse.fail_fast(),
+            "--max-errors" if reads_maps => {
+                let max_errors = parse_value(command, &arg, value()?)?;
+                options.parse = options.parse.max_errors(max_errors);
+            }
+            "--threshold" if reads_cells => {
+                parsed.threshold = Some(parse_value(command, &arg, value()?)?);
+            }
+            "--plain" if reads_maps => parsed.colour = false,
+            "--charset" if has_charset => {
+                charset = named(command, &arg, value()?, island::map_format)?;
+            }
+            "--sea" if has_charset => {
+                sea = Some(named(command, &arg, value()?, single_char)?);
+            }
+            "--earth" if has_charset => {
+                earth = Some(named(command, &arg, value()?, single_char)?);
+            }
+            "--marks" if reads_cells => {
+                marks = Some(named(command, &arg, value()?, island::alphabet)?);
+            }
+            "--level" if command == Sweep => {
+                parsed.level = Some(parse_value(command, &arg, value()?)?);
+            }
+            "--width" if command == Generate => {
+                parsed.generate.width = parse_value(command, &arg, value()?)?;
+            }
+            "--height" if command == Generate => {
+                parsed.generate.height = parse_value(command, &arg, value()?)?;
+            }
+            "--seed" if command == Generate => {
+                parsed.generate.seed = parse_value(command, &arg, value()?)?;
+            }
+            "--density" if command == Generate => {
+                let density = parse_value(command, &arg, value()?)?;
+                if !(0.0..=1.0).contains(&density) {
+                    let message = format!("invalid value '{}' for '{}'", density, arg);
+                    return Err(Failure::usage(Some(command), message));
+                }
+                parsed.generate.density = Some(density);
+            }
+            "--terrain" if command == Generate => {
+                parsed.generate.terrain = named(command, &arg, value()?, island::terrain)?;
+            }
+            "--scale" if command == Generate => {
+                parsed.generate.scale = Some(parse_value(command, &arg, value()?)?);
+            }
+            "--octaves" if command == Generate => {
+                parsed.generate.octaves = Some(parse_value(command, &arg, value()?)?);
+            }
+            "--steps" if command == Generate => {
+                parsed.generate.steps = Some(parse_value(command, &arg, value()?)?);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                let message = format!("unknown option '{}'", arg);
+                return Err(Failure::usage(Some(command), message));
+            }
+            _ if reads_maps => parsed.inputs.push(arg),
+            _ => {
+                let message = format!("unexpected argument '{}'", arg);
+                return Err(Failure::usage(Some(command), message));
+            }
+        }
+    }
+
+    if reads_maps && parsed.inputs.is_empty() {
+        return Err(Failure::usage(Some(command), "no input file"));
+    }
+    if let Some(threads) = threads {
+        if !parallel {
+            let message = "'--threads' needs '--labeler parallel'";
+            return Err(Failure::usage(Some(command), message));
+        }
+        parsed.options.labeler = Box::new(island::Parallel::new(threads));
+    }
+    if parsed.stream && parsed.inputs.iter().any(|input| input == "-") {
+        let message = "the standard input cannot be streamed";
+        return Err(Failure::usage(Some(command), message));
+    }
+    if parsed.stream {
+        // the stream labeler parses strictly and only renders labels
+        let unstreamable = [
+            ("--lenient", parsed.lenient),
+            ("--labeler", parsed.labeler),
+            ("--preserve-marks", parsed.options.preserve_marks),
+            ("--lakes", parsed.options.lake_encoder.is_some()),
+            ("--format json", parsed.json),
+            ("--ppm", parsed.ppm.is_some()),
+        ];
+
+        if let Some((flag, _)) = unstreamable.iter().find(|(_, given)| *given) {
+            let message = format!("'--stream' cannot be used with '{}'", flag);
+            return Err(Failure::usage(Some(command), message));
+        }
+    }
+
+    parsed.format = island::MapFormat::new(
+        sea.unwrap_or(charset.sea()),
+        earth.unwrap_or(charset.earth()),
+        marks.unwrap_or(charset.marks()),
+    )
+    .map_err(|error| Failure::usage(Some(command), error))?;
+    parsed.options.parse = parsed.options.parse.format(parsed.format);
+
+    if !encoding {
+        parsed.options.encoder = island::format_encoder(&parsed.format);
+    }
+    let encoders = [
+        ("--encoding", Some(&parsed.options.encoder)),
+        ("--lakes", parsed.options.lake_encoder.as_ref()),
+    ];
+    for (flag, encoder) in encoders {
+        if encoder.is_some_and(|encoder| encoder.collides(&parsed.format)) {
+            let message = format!("'{}' renders labels as the sea or earth character", flag);
+            return Err(Failure::usage(Some(command), message));
+        }
+    }
+
+    Ok(Invocation::Run(Box::new(parsed)))
+}
+
+/// Value of `flag` looked up by name with `lookup`
+fn named<T, F>(command: Command, flag: &str, name: String, lookup: F) -> Result<T, Failure>
+where
+    F: Fn(&str) -> Option<T>,
+{
+    lookup(&name).ok_or_else(|| {
+        let message = format!("invalid value '{}' for '{}'", name, flag);
+        Failure::usage(Some(command), message)
+    })
+}
+
+/// Value of `flag` parsed with [`FromStr`]
+fn parse_value<T>(command: Command, flag: &str, value: String) -> Result<T, Failure>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|error| {
+        let message = format!("invalid value '{}' for '{}': {}", value, flag, error);
+        Failure::usage(Some(command), message)
+    })
+}
+
+/// The only character of `arg`
+fn single_char(arg: &str) -> Option<char> {
+    let mut chars = arg.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
 
-    let args: Vec<String> = env::args().skip(1).collect();
-    let filepath = args.get(0).ok_or(USAGE)?;
+/*
+ * Failure
+ */
 
-    let raw_map = fs::read_to_string(filepath)?;
+/// Why a command failed: its [`exit`] code and the message printed on the standard error
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn usage(command: Option<Command>, message: impl fmt::Display) -> Self {
+        let usage = command.map_or(USAGE, Command::usage);
+        Failure {
+            code: exit::USAGE,
+            message: format!("error: {}\n\n{}", message, usage),
+        }
+    }
+
+    fn io(input: &str, error: io::Error) -> Self {
+        Failure {
+            code: exit::IO,
+            message: format!("error: {}: {}\n", origin(input), error),
+        }
+    }
+
+    fn parse(message: String) -> Self {
+        Failure {
+            code: exit::PARSE,
+            message,
+        }
+    }
+
+    fn stream(input: &str, error: StreamError) -> Self {
+        match error {
+            StreamError::Io(error) => Failure::io(input, error),
+            StreamError::Map(island::ErrorList(errors)) => Failure::parse(
+                errors
+                    .iter()
+                    .map(|error| format!("error: {}: {}\n", origin(input), error))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Print the message and get the exit code
+    fn report(self) -> i32 {
+        eprint!("{}", self.message);
+        self.code
+    }
+}
+
+/// The output could not be written
+impl From<io::Error> for Failure {
+    fn from(error: io::Error) -> Self {
+        Failure {
+            code: exit::IO,
+            message: format!("error: cannot write the output: {}\n", error),
+        }
+    }
+}
+
+/*
+ * Commands
+ */
+
+fn run(args: &Args) -> i32 {
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(error) => return Failure::io(path, error).report(),
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut output = BufWriter::new(output);
+    let mut code = 0;
+    let mut fail = |failure: Failure| {
+        let failure_code = failure.report();
+        if code == 0 {
+            code = failure_code;
+        }
+    };
+
+    if args.command == Command::Generate {
+        if let Err(failure) = generate(args, &mut output) {
+            fail(failure);
+        }
+    }
+
+    for input in &args.inputs {
+        let result = match args.command {
+            Command::Label => label(args, input, &mut output),
+            Command::Count => count(args, input, &mut output),
+            Command::Stats => stats(args, input, &mut output),
+            Command::Verify => verify(args, input, &mut output),
+            Command::Regions => regions(args, input, &mut output),
+            Command::Sweep => sweep(args, input, &mut output),
+            Command::Generate => unreachable!("generate has no input"),
+        };
+
+        if let Err(failure) = result {
+            fail(failure);
+        }
+    }
+
+    if let Err(error) = output.flush() {
+        fail(error.into());
+    }
+
+    code
+}
+
+fn label(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    if args.stream {
+        return label_stream(args, input, output);
+    }
+
+    let encoder = args.options.encoder.as_ref();
+    let (map, warnings) = load(args, input, output)?;
+    let map = island::label_map(map, &args.options);
+
+    if let Some(encoding) = args.ppm {
+        output.write_all(&island::write_ppm(&map, encoding))?;
+        return Ok(());
+    }
+    if args.json {
+        let stats = island::Stats::new(&map, &args.options.connectivity);
+        let report = island::report(&map, &stats, encoder, &warnings);
+        writeln!(output, "{}", report)?;
+        return Ok(());
+    }
+
+    check_overflow(input, last_label(&map), encoder)?;
+    let raw_map = match &args.options.lake_encoder {
+        Some(lake_encoder) => {
+            let lakes = island::Lakes::new(&map, &args.options.connectivity);
+            lakes.render(&map, encoder, lake_encoder.as_ref())
+        }
+        None => map.render(encoder),
+    };
+
+    write_header(args, input, output)?;
+    write!(output, "{}", raw_map)?;
+    Ok(())
+}
+
+/// `label --stream`, the file being read twice
+fn label_stream(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    let encoder = args.options.encoder.as_ref();
+    let file = File::open(input).map_err(|error| Failure::io(input, error))?;
+    let mut reader = BufReader::new(file);
+
+    let labeler =
+        island::StreamLabeler::scan(&mut reader, &args.options.connectivity, &args.options.parse)
+            .map_err(|error| Failure::stream(input, error))?;
+    check_overflow(input, labeler.island_count().checked_sub(1), encoder)?;
+
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|error| Failure::io(input, error))?;
+    write_header(args, input, output)?;
+    labeler
+        .relabel(reader, output, encoder)
+        .map_err(|error| Failure::stream(input, error))
+}
+
+fn count(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    // only a map in memory can be repaired or labelled by another engine
+    let island_count = match is_image(input) || args.lenient || args.labeler {
+        true => {
+            let (map, _) = load(args, input, output)?;
+            last_label(&island::label_map(map, &args.options)).map_or(0, |label| label + 1)
+        }
+        false => {
+            let reader: Box<dyn BufRead> = match input {
+                "-" => Box::new(io::stdin().lock()),
+                _ => {
+                    let file = File::open(input).map_err(|error| Failure::io(input, error))?;
+                    Box::new(BufReader::new(file))
+                }
+            };
+            island::count_stream(reader, &args.options)
+                .map_err(|error| Failure::stream(input, error))?
+        }
+    };
+
+    match args.inputs.len() {
+        1 => writeln!(output, "{}", island_count)?,
+        _ => writeln!(output, "{}: {}", origin(input), island_count)?,
+    }
+    Ok(())
+}
+
+fn stats(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    let (map, warnings) = load(args, input, output)?;
+    let map = island::label_map(map, &args.options);
+    let stats = island::Stats::new(&map, &args.options.connectivity);
+
+    if args.json {
+        let encoder = args.options.encoder.as_ref();
+        writeln!(
+            output,
+            "{}",
+            island::report(&map, &stats, encoder, &warnings)
+        )?;
+        return Ok(());
+    }
+
+    write_header(args, input, output)?;
+    write!(output, "{}", stats)?;
+    Ok(())
+}
+
+fn verify(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    let raw_map = read_text(input)?;
 
-    let updated_raw_map = island::run(&raw_map)?;
-    print!("{}", updated_raw_map);
+    island::verify(&raw_map, &args.options)
+        .map_err(|errors| Failure::parse(diagnostics(args, &raw_map, input).render(&errors)))?;
+    writeln!(output, "{}: ok", origin(input))?;
+    Ok(())
+}
+
+fn regions(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    let raw_map = read_text(input)?;
+    let diagnostics = diagnostics(args, &raw_map, input);
+
+    let (grid, warnings) = args
+        .options
+        .parse
+        .parse_classes(&raw_map)
+        .map_err(|errors| Failure::parse(diagnostics.render(&errors)))?;
+    eprint!("{}", diagnostics.render_warnings(&warnings));
+
+    write_header(args, input, output)?;
+    write!(
+        output,
+        "{}",
+        island::Regions::partial(grid, &args.options.connectivity)
+    )?;
+    Ok(())
+}
+
+fn sweep(args: &Args, input: &str, output: &mut dyn Write) -> Result<(), Failure> {
+    let raw_map = read_text(input)?;
+    let diagnostics = diagnostics(args, &raw_map, input);
+
+    let (heights, warnings) = args
+        .options
+        .parse
+        .parse_heights(&raw_map)
+        .map_err(|errors| Failure::parse(diagnostics.render(&errors)))?;
+    eprint!("{}", diagnostics.render_warnings(&warnings));
+
+    let connectivity = &args.options.connectivity;
+    let islands = args.level.map(|level| {
+        let map = island::land(&heights, level);
+        (level, island::label_map(map, &args.options))
+    });
+    if let Some((_, map)) = &islands {
+        check_overflow(input, last_label(map), args.options.encoder.as_ref())?;
+    }
 
+    write_header(args, input, output)?;
+    write!(output, "{}", island::Sweep::new(&heights, connectivity))?;
+    if let Some((level, map)) = islands {
+        writeln!(output, "\nislands at level {}:", level)?;
+        write!(output, "{}", map.render(args.options.encoder.as_ref()))?;
+    }
     Ok(())
 }
+
+fn generate(args: &Args, output: &mut dyn Write) -> Result<(), Failure> {
+    let GenerateArgs {
+        width,
+        height,
+        seed,
+        ..
+    } = args.generate;
+    let map = island::Generator::new(width, height)
+        .seed(seed)
+        .generate(&args.generate.terrain());
+
+    write!(
+        output,
+        "{}",
+        map.render_with("", |_, cell| args.format.char(cell).to_string())
+    )?;
+    Ok(())
+}
+
+/*
+ * Inputs
+ */
+
+/// Name of an input in messages
+fn origin(input: &str) -> &str {
+    match input {
+        "-" => "<stdin>",
+        _ => input,
+    }
+}
+
+fn is_image(input: &str) -> bool {
+    [".pbm", ".pgm", ".pnm"]
+        .iter()
+        .any(|extension| input.ends_with(extension))
+}
+
+fn read(input: &str) -> Result<Vec<u8>, Failure> {
+    let result = match input {
+        "-" => {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+        }
+        _ => fs::read(input),
+    };
+
+    result.map_err(|error| Failure::io(input, error))
+}
+
+fn read_text(input: &str) -> Result<String, Failure> {
+    String::from_utf8(read(input)?)
+        .map_err(|error| Failure::io(input, io::Error::new(io::ErrorKind::InvalidData, error)))
+}
+
+fn diagnostics<'a>(args: &Args, raw_map: &'a str, input: &'a str) -> island::Diagnostics<'a> {
+    let colour = args.colour && io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    island::Diagnostics::new(raw_map)
+        .origin(origin(input))
+        .colour(colour)
+}
+
+/// Parse an input and get the warnings of a lenient parsing, which are printed
+///
+/// With `--format json`, parse errors are written to `output` as a JSON report instead, and
+/// warnings are left to the report of the map
+fn load(
+    args: &Args,
+    input: &str,
+    output: &mut dyn Write,
+) -> Result<(Map, island::ErrorList), Failure> {
+    if is_image(input) {
+        let map = island::read_netpbm(&read(input)?, args.threshold)
+            .map_err(|error| Failure::parse(format!("error: {}: {}\n", origin(input), error)))?;
+        return Ok((map, island::ErrorList(vec![])));
+    }
+
+    let raw_map = read_text(input)?;
+    let diagnostics = diagnostics(args, &raw_map, input);
+
+    match args.options.parse.parse(&raw_map) {
+        Ok((map, warnings)) => {
+            if !args.json {
+                eprint!("{}", diagnostics.render_warnings(&warnings));
+            }
+            Ok((map, warnings))
+        }
+        Err(errors) if args.json => {
+            writeln!(output, "{}", island::Json::from(&errors))?;
+            Err(Failure::parse(String::new()))
+        }
+        Err(errors) => Err(Failure::parse(diagnostics.render(&errors))),
+    }
+}
+
+/// `==> name <==` before the output of every input, when there are several
+fn write_header(args: &Args, input: &str, output: &mut dyn Write) -> io::Result<()> {
+    match args.inputs.len() {
+        1 => Ok(()),
+        _ => writeln!(output, "==> {} <==", origin(input)),
+    }
+}
+
+/// Largest label of a labelled map
+fn last_label(map: &Map) -> Option<Label> {
+    map.cursor()
+        .iter()
+        .filter_map(|cursor| match cursor.read() {
+            Cell::MarkedEarth(label) => Some(label),
+            _ => None,
+        })
+        .max()
+}
+
+/// Fail when `encoder` cannot render every label up to `last_label`
+fn check_overflow(
+    input: &str,
+    last_label: Option<Label>,
+    encoder: &dyn LabelEncoder,
+) -> Result<(), Failure> {
+    match last_label {
+        Some(label) if encoder.encode(label).is_none() => Err(Failure {
+            code: exit::OVERFLOW,
+            message: format!(
+                "error: {}: island {} cannot be rendered by the encoding, try `--encoding csv`\n",
+                origin(input),
+                label
+            ),
+        }),
+        _ => Ok(()),
+    }
+}
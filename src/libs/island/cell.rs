@@ -1,3 +1,5 @@
+use super::format::MapFormat;
+use super::label::Label;
 use std::convert::{From, TryFrom};
 use std::fmt;
 
@@ -7,7 +9,6 @@ use std::fmt;
 mod constants {
     pub const SEA_CHAR: char = ' ';
     pub const EARTH_CHAR: char = '#';
-    pub const RADIX_BASE: u32 = 36;
 }
 
 pub use constants::*;
@@ -39,9 +40,9 @@ pub mod error {
     impl fmt::Display for Increment {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             match *self {
-            Increment::Overflow { cell } => write!(f, "cannot increment the cell '{}' because this is the maximum allowed character for marked earth", char::from(cell)),
-            Increment::NotValidCell {cell} => write!(f, "cannot increment the cell '{}' because only Cell::MarkedEarth can be incremented", char::from(cell))
-        }
+                Increment::Overflow { cell } => write!(f, "cannot increment the cell {:?} because this is the maximum allowed label for marked earth", cell),
+                Increment::NotValidCell { cell } => write!(f, "cannot increment the cell '{}' because only Cell::MarkedEarth can be incremented", char::from(cell))
+            }
         }
     }
 
@@ -61,60 +62,35 @@ pub mod error {
     impl std::error::Error for Increment {}
 }
 
-/*
- * Utils
- */
-mod utils {
-    use super::*;
-
-    pub fn is_valid_cell_char(c: char) -> bool {
-        match c {
-            _ if c.is_digit(RADIX_BASE) => true,
-            SEA_CHAR => true,
-            EARTH_CHAR => true,
-            _ => false,
-        }
-    }
-
-    pub fn increment_char(c: char) -> Option<char> {
-        let n = c.to_digit(RADIX_BASE)?;
-        char::from_digit(n + 1, RADIX_BASE)
-    }
-}
-
 /*
  * Cell
  */
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Cell {
     Sea,
     Earth,
-    MarkedEarth(char),
+    MarkedEarth(Label),
 }
 
 impl Cell {
     pub fn is_markable(&self) -> bool {
-        match self {
-            Cell::Earth => true,
-            _ => false,
-        }
+        matches!(self, Cell::Earth)
     }
 
     pub fn is_marked(&self) -> bool {
-        match self {
-            Cell::MarkedEarth(_) => true,
-            _ => false,
-        }
+        matches!(self, Cell::MarkedEarth(_))
     }
 
     pub fn increment(&mut self) -> Result<(), error::Increment> {
         let cell = *self;
         match *self {
-            Cell::MarkedEarth(c) => {
-                let next_char =
-                    utils::increment_char(c).ok_or(error::Increment::Overflow { cell })?;
+            Cell::MarkedEarth(label) => {
+                let next_label = label
+                    .checked_add(1)
+                    .ok_or(error::Increment::Overflow { cell })?;
 
-                *self = Cell::MarkedEarth(next_char);
+                *self = Cell::MarkedEarth(next_label);
                 Ok(())
             }
             _ => Err(error::Increment::NotValidCell { cell }),
@@ -122,26 +98,47 @@ impl Cell {
     }
 }
 
+/*
+ * CellKind
+ */
+
+/// # Kind of a [`Cell`], without its label
+///
+/// A single byte, what a [`Map`](super::Map) stores for every cell, labels being stored apart
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CellKind {
+    Sea,
+    Earth,
+    MarkedEarth,
+}
+
+impl Cell {
+    pub fn kind(&self) -> CellKind {
+        match self {
+            Cell::Sea => CellKind::Sea,
+            Cell::Earth => CellKind::Earth,
+            Cell::MarkedEarth(_) => CellKind::MarkedEarth,
+        }
+    }
+}
+
+/// Parse a character of the [`MapFormat::DEFAULT`] format, see [`MapFormat::cell`] for others
 impl TryFrom<char> for Cell {
     type Error = error::Parse;
 
     fn try_from(c: char) -> Result<Cell, error::Parse> {
-        match c {
-            SEA_CHAR => Ok(Cell::Sea),
-            EARTH_CHAR => Ok(Cell::Earth),
-            _ if utils::is_valid_cell_char(c) => Ok(Cell::MarkedEarth(c)),
-            _ => Err(error::Parse(c)),
-        }
+        MapFormat::DEFAULT.cell(c)
     }
 }
 
+/// Character of the [`MapFormat::DEFAULT`] format, see [`MapFormat::char`] for others
+///
+/// Note: labels that do not fit in a single base36 digit are rendered as [`EARTH_CHAR`],
+/// use a [`LabelEncoder`](super::label::LabelEncoder) to render them
 impl From<Cell> for char {
     fn from(cell: Cell) -> Self {
-        match cell {
-            Cell::Sea => SEA_CHAR,
-            Cell::Earth => EARTH_CHAR,
-            Cell::MarkedEarth(c) => c,
-        }
+        MapFormat::DEFAULT.char(cell)
     }
 }
 
@@ -156,18 +153,13 @@ mod tests {
         use super::*;
 
         #[test]
-        fn radix_base() {
-            assert!(RADIX_BASE > 0 && RADIX_BASE < 37);
+        fn sea_constant_is_not_a_mark() {
+            assert_eq!(MapFormat::DEFAULT.marks().label(SEA_CHAR), None);
         }
 
         #[test]
-        fn sea_constant_is_not_a_digit() {
-            assert!(!SEA_CHAR.is_digit(RADIX_BASE));
-        }
-
-        #[test]
-        fn earth_constant_is_not_a_digit() {
-            assert!(!EARTH_CHAR.is_digit(RADIX_BASE));
+        fn earth_constant_is_not_a_mark() {
+            assert_eq!(MapFormat::DEFAULT.marks().label(EARTH_CHAR), None);
         }
     }
 
@@ -189,7 +181,8 @@ mod tests {
 
         #[test]
         fn marked_earth_cell_to_char() -> Result<(), error::Parse> {
-            assert_eq!(Cell::try_from('1')?, Cell::MarkedEarth('1'));
+            assert_eq!(Cell::try_from('1')?, Cell::MarkedEarth(1));
+            assert_eq!(Cell::try_from('z')?, Cell::MarkedEarth(35));
             Ok(())
         }
 
@@ -205,7 +198,13 @@ mod tests {
 
         #[test]
         fn marked_earth_char_to_cell() {
-            assert_eq!(char::from(Cell::MarkedEarth('0')), '0');
+            assert_eq!(char::from(Cell::MarkedEarth(0)), '0');
+            assert_eq!(char::from(Cell::MarkedEarth(35)), 'z');
+        }
+
+        #[test]
+        fn unrepresentable_marked_earth_char_to_cell() {
+            assert_eq!(char::from(Cell::MarkedEarth(36)), EARTH_CHAR);
         }
 
         // Conversion error
@@ -220,12 +219,20 @@ mod tests {
     mod methods {
         use super::*;
 
+        #[test]
+        fn kind() {
+            assert_eq!(Cell::Sea.kind(), CellKind::Sea);
+            assert_eq!(Cell::Earth.kind(), CellKind::Earth);
+            assert_eq!(Cell::MarkedEarth(42).kind(), CellKind::MarkedEarth);
+            assert_eq!(std::mem::size_of::<CellKind>(), 1);
+        }
+
         // Cell::is_markable method
         #[test]
         fn is_markable() {
-            assert_eq!(true, Cell::Earth.is_markable());
-            assert_eq!(false, Cell::Sea.is_markable());
-            assert_eq!(false, Cell::MarkedEarth('0').is_markable());
+            assert!(Cell::Earth.is_markable());
+            assert!(!Cell::Sea.is_markable());
+            assert!(!Cell::MarkedEarth(0).is_markable());
         }
 
         mod increment {
@@ -233,29 +240,27 @@ mod tests {
 
             #[test]
             fn zero_cell() {
-                let mut cell = Cell::MarkedEarth('0');
+                let mut cell = Cell::MarkedEarth(0);
                 let result = cell.increment();
                 assert_eq!(result, Ok(()));
-                assert_eq!(cell, Cell::MarkedEarth('1'));
+                assert_eq!(cell, Cell::MarkedEarth(1));
             }
 
             #[test]
-            fn all() {
-                let mut cell = Cell::MarkedEarth('0');
-                let mut counter = 1;
+            fn beyond_radix_base() {
+                let mut cell = Cell::MarkedEarth(0);
 
-                while let Ok(()) = cell.increment() {
-                    counter += 1;
+                for _ in 0..36 * 2 {
+                    assert_eq!(cell.increment(), Ok(()));
                 }
 
-                assert_eq!(counter, RADIX_BASE);
+                assert_eq!(cell, Cell::MarkedEarth(36 * 2));
             }
 
             // Increment errors
             #[test]
             fn overflow() {
-                let last_char = char::from_digit(RADIX_BASE - 1, RADIX_BASE).unwrap();
-                let mut cell = Cell::MarkedEarth(last_char);
+                let mut cell = Cell::MarkedEarth(Label::MAX);
 
                 assert_eq!(cell.increment(), Err(error::Increment::Overflow { cell }));
             }
@@ -0,0 +1,212 @@
+use super::cursor::{Direction, DIAGONAL_DIRECTIONS, DIRECTIONS};
+use std::fmt;
+use std::str::FromStr;
+
+/*
+ * Errors
+ */
+pub mod error {
+    use super::*;
+
+    // Parsing error Display
+    #[derive(PartialEq)]
+    pub struct Parse(pub String);
+
+    impl fmt::Display for Parse {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let Parse(s) = self;
+            write!(
+                f,
+                "invalid connectivity '{}', expected '4', '8', 'knight' or a list of 'dx:dy' offsets",
+                s
+            )
+        }
+    }
+
+    impl fmt::Debug for Parse {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self)
+        }
+    }
+
+    impl std::error::Error for Parse {}
+}
+
+/*
+ * Connectivity
+ */
+
+/// # Neighbourhood used to connect earth cells into islands
+///
+/// - [`Connectivity::four`]: cells sharing an edge (the default)
+/// - [`Connectivity::eight`]: cells sharing an edge or a corner
+/// - [`Connectivity::custom`]: any set of `(dx, dy)` offsets, e.g. [`Connectivity::knight`]
+///
+/// Being connected is a symmetric relation, so every offset comes with its opposite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connectivity(Vec<Direction>);
+
+impl Connectivity {
+    pub fn four() -> Self {
+        Connectivity(DIRECTIONS.to_vec())
+    }
+
+    pub fn eight() -> Self {
+        Connectivity([DIRECTIONS, DIAGONAL_DIRECTIONS].concat())
+    }
+
+    /// The 8 moves of a chess knight
+    pub fn knight() -> Self {
+        Connectivity::custom(&[(1, 2), (2, 1), (2, -1), (1, -2)])
+    }
+
+    /// Build a neighbourhood from `(dx, dy)` offsets
+    ///
+    /// opposite offsets are added, `(0, 0)` and duplicates are dropped
+    pub fn custom(offsets: &[(isize, isize)]) -> Self {
+        let mut directions: Vec<Direction> = vec![];
+
+        for &(dx, dy) in offsets {
+            for offset in [(dx, dy), (-dx, -dy)] {
+                let known = directions.iter().any(|dir| dir.offset() == offset);
+
+                if offset != (0, 0) && !known {
+                    directions.push(Direction::Offset(offset.0, offset.1));
+                }
+            }
+        }
+
+        Connectivity(directions)
+    }
+
+    /// Neighbourhood of the sea around islands connected with `self`
+    ///
+    /// water flows between diagonal land cells of 4-connected islands, so the dual of
+    /// [`Connectivity::four`] is [`Connectivity::eight`], every other neighbourhood gets 4-connected sea
+    pub fn dual(&self) -> Self {
+        // compare the neighbours, not the order or kind of directions they were built from
+        let sorted = |connectivity: &Connectivity| {
+            let mut offsets = connectivity.offsets();
+            offsets.sort_unstable();
+            offsets
+        };
+
+        if sorted(self) == sorted(&Connectivity::four()) {
+            Connectivity::eight()
+        } else {
+            Connectivity::four()
+        }
+    }
+
+    pub fn directions(&self) -> &[Direction] {
+        let Connectivity(directions) = self;
+        directions
+    }
+
+    /// `(dx, dy)` offsets of every neighbour
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        self.directions().iter().map(|dir| dir.offset()).collect()
+    }
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Connectivity::four()
+    }
+}
+
+/// Parse `"4"`, `"8"`, `"knight"` or a comma separated list of `dx:dy` offsets (e.g. `"1:2,2:1"`)
+impl FromStr for Connectivity {
+    type Err = error::Parse;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_error = || error::Parse(s.to_string());
+
+        match s {
+            "4" => Ok(Connectivity::four()),
+            "8" => Ok(Connectivity::eight()),
+            "knight" => Ok(Connectivity::knight()),
+            _ => {
+                let offsets = s
+                    .split(',')
+                    .map(|offset| {
+                        let (dx, dy) = offset.split_once(':')?;
+                        Some((dx.trim().parse().ok()?, dy.trim().parse().ok()?))
+                    })
+                    .collect::<Option<Vec<(isize, isize)>>>()
+                    .ok_or_else(parse_error)?;
+
+                let connectivity = Connectivity::custom(&offsets);
+                if connectivity.directions().is_empty() {
+                    return Err(parse_error());
+                }
+
+                Ok(connectivity)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbour_counts() {
+        assert_eq!(Connectivity::four().directions().len(), 4);
+        assert_eq!(Connectivity::eight().directions().len(), 8);
+        assert_eq!(Connectivity::knight().directions().len(), 8);
+    }
+
+    #[test]
+    fn custom_is_symmetric() {
+        let connectivity = Connectivity::custom(&[(0, 0), (1, 2), (-1, -2), (0, 1)]);
+
+        assert_eq!(
+            connectivity.offsets(),
+            vec![(1, 2), (-1, -2), (0, 1), (0, -1)]
+        );
+    }
+
+    #[test]
+    fn dual() {
+        assert_eq!(Connectivity::four().dual(), Connectivity::eight());
+        assert_eq!(Connectivity::eight().dual(), Connectivity::four());
+        assert_eq!(Connectivity::knight().dual(), Connectivity::four());
+    }
+
+    #[test]
+    fn dual_of_custom_four() {
+        let four: Connectivity = "0:1,1:0".parse().unwrap();
+
+        assert_ne!(four, Connectivity::four());
+        assert_eq!(four.dual(), Connectivity::eight());
+    }
+
+    mod parse {
+        use super::*;
+
+        #[test]
+        fn presets() {
+            assert_eq!("4".parse(), Ok(Connectivity::four()));
+            assert_eq!("8".parse(), Ok(Connectivity::eight()));
+            assert_eq!("knight".parse(), Ok(Connectivity::knight()));
+        }
+
+        #[test]
+        fn offsets() {
+            assert_eq!(
+                "1:2, 2:1".parse(),
+                Ok(Connectivity::custom(&[(1, 2), (2, 1)]))
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            for s in ["", "6", "1:", "1:2,x:1", "0:0"] {
+                let result: Result<Connectivity, _> = s.parse();
+                assert_eq!(result, Err(error::Parse(s.to_string())));
+            }
+        }
+    }
+}
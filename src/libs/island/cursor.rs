@@ -1,57 +1,144 @@
-use super::cell::Cell;
+use super::grid::Grid;
 use super::map::Map;
 
+/// A move from a cell to one of its neighbours, `Offset` is a `(dx, dy)` move
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Direction {
     Up,
     Right,
     Down,
     Left,
+    UpRight,
+    DownRight,
+    DownLeft,
+    UpLeft,
+    Offset(isize, isize),
 }
 
 use Direction::*;
 
 pub const DIRECTIONS: [Direction; 4] = [Up, Right, Down, Left];
 
-#[derive(Debug, Copy, Clone)]
-pub struct Cursor<'a> {
-    map: &'a Map,
+pub const DIAGONAL_DIRECTIONS: [Direction; 4] = [UpRight, DownRight, DownLeft, UpLeft];
+
+impl Direction {
+    /// The `(dx, dy)` move of this direction, `y` grows downward
+    pub fn offset(&self) -> (isize, isize) {
+        match *self {
+            Up => (0, -1),
+            Right => (1, 0),
+            Down => (0, 1),
+            Left => (-1, 0),
+            UpRight => (1, -1),
+            DownRight => (1, 1),
+            DownLeft => (-1, 1),
+            UpLeft => (-1, -1),
+            Offset(dx, dy) => (dx, dy),
+        }
+    }
+}
+
+/// # Cells read by a [`Cursor`]
+///
+/// A [`Grid`] of copyable cells, or a [`Map`] which reads its [`Cell`](super::Cell)s from packed
+/// kinds and labels
+pub trait Raster {
+    type Cell;
+
+    /// Number of cells of every line
+    fn width(&self) -> usize;
+
+    /// Number of lines
+    fn height(&self) -> usize;
+
+    /// The cell at `(x, y)`, which is inside of the raster
+    fn read(&self, coord: (usize, usize)) -> Self::Cell;
+
+    /// `true` when `(x, y)` is inside of the raster
+    fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width() && y < self.height()
+    }
+}
+
+impl<T: Copy> Raster for Grid<T> {
+    type Cell = T;
+
+    fn width(&self) -> usize {
+        Grid::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Grid::height(self)
+    }
+
+    fn read(&self, coord: (usize, usize)) -> T {
+        self[coord]
+    }
+}
+
+/// # Position on a [`Raster`], moving from cell to cell
+///
+/// Cursors of a [`Map`] read [`Cell`](super::Cell)s, the default raster
+#[derive(Debug)]
+pub struct Cursor<'a, R = Map> {
+    raster: &'a R,
     x: usize,
     y: usize,
 }
 
-impl<'a> Cursor<'a> {
-    pub fn new(map: &'a Map) -> Cursor<'a> {
-        Cursor { map, x: 0, y: 0 }
+// derived impls would require `R: Copy`, a cursor only copies a reference
+impl<R> Clone for Cursor<'_, R> {
+    fn clone(&self) -> Self {
+        *self
     }
+}
 
-    pub fn read(&self) -> Cell {
-        let Cursor { map, x, y } = *self;
-        return map
-            .get((x, y))
-            .expect(format!("[FATAL] invalid cursor read at x:{} y:{}", x, y).as_str());
+impl<R> Copy for Cursor<'_, R> {}
+
+impl<'a, R> Cursor<'a, R> {
+    pub fn new(raster: &'a R) -> Cursor<'a, R> {
+        Cursor { raster, x: 0, y: 0 }
+    }
+}
+
+impl<'a, T> Cursor<'a, Grid<T>> {
+    /// The cell under the cursor, by reference
+    pub fn cell(&self) -> &'a T {
+        let Cursor { raster, x, y } = *self;
+        match raster.contains((x, y)) {
+            true => &raster[(x, y)],
+            false => panic!("[FATAL] invalid cursor read at x:{} y:{}", x, y),
+        }
     }
+}
 
-    pub fn get(&self, (x, y): (usize, usize)) -> Option<Cursor<'a>> {
-        let Cursor { map, .. } = self;
-        map.get((x, y)).and(Some(Cursor { map, x, y }))
+impl<'a, R: Raster> Cursor<'a, R> {
+    /// The cell under the cursor
+    pub fn read(&self) -> R::Cell {
+        let Cursor { raster, x, y } = *self;
+        match raster.contains((x, y)) {
+            true => raster.read((x, y)),
+            false => panic!("[FATAL] invalid cursor read at x:{} y:{}", x, y),
+        }
     }
 
-    pub fn move_next_line(&self) -> Option<Cursor<'a>> {
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<Cursor<'a, R>> {
+        let Cursor { raster, .. } = *self;
+        raster.contains((x, y)).then_some(Cursor { raster, x, y })
+    }
+
+    pub fn move_next_line(&self) -> Option<Cursor<'a, R>> {
         self.get((0, self.y + 1))
     }
 
-    pub fn move_dir(&self, dir: Direction) -> Option<Cursor<'a>> {
+    pub fn move_dir(&self, dir: Direction) -> Option<Cursor<'a, R>> {
         let Cursor { x, y, .. } = *self;
-        match dir {
-            Up if y > 0 => self.get((x, y - 1)),
-            Left if x > 0 => self.get((x - 1, y)),
-            Down => self.get((x, y + 1)),
-            Right => self.get((x + 1, y)),
-            _ => None,
-        }
+        let (dx, dy) = dir.offset();
+
+        self.get((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?))
     }
 
-    pub fn iter(&self) -> CursorIter<'a> {
+    pub fn iter(&self) -> CursorIter<'a, R> {
         CursorIter::new(*self)
     }
 
@@ -60,22 +147,23 @@ impl<'a> Cursor<'a> {
     }
 }
 
-pub struct CursorIter<'a> {
-    cursor: Cursor<'a>,
+pub struct CursorIter<'a, R = Map> {
+    cursor: Cursor<'a, R>,
     nextable: bool,
 }
 
-impl<'a> CursorIter<'a> {
-    fn new(cursor: Cursor<'a>) -> CursorIter<'a> {
+impl<'a, R: Raster> CursorIter<'a, R> {
+    fn new(cursor: Cursor<'a, R>) -> CursorIter<'a, R> {
         CursorIter {
+            // an empty raster has no cell to start from
+            nextable: cursor.raster.contains(cursor.coord()),
             cursor,
-            nextable: true,
         }
     }
 }
 
-impl<'a> Iterator for CursorIter<'a> {
-    type Item = Cursor<'a>;
+impl<'a, R: Raster> Iterator for CursorIter<'a, R> {
+    type Item = Cursor<'a, R>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if !self.nextable {
@@ -96,6 +184,7 @@ impl<'a> Iterator for CursorIter<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::libs::island::cell::Cell;
     use Cell::*;
 
     #[test]
@@ -103,13 +192,41 @@ mod tests {
         let map: Map = "# \n0".parse().unwrap();
         let cells: Vec<Cell> = map.cursor().iter().map(|c| c.read()).collect();
 
-        assert_eq!(cells, vec![Earth, Sea, MarkedEarth('0')]);
+        // Note: short lines are padded with sea
+        assert_eq!(cells, vec![Earth, Sea, MarkedEarth(0), Sea]);
+    }
+
+    #[test]
+    fn move_dir() {
+        let map: Map = "# #\n 0 \n# #".parse().unwrap();
+        let center = map.cursor().get((1, 1)).unwrap();
+        let corner = map.cursor();
+
+        assert_eq!(center.move_dir(Up).unwrap().read(), Sea);
+        assert_eq!(center.move_dir(UpLeft).unwrap().coord(), (0, 0));
+        assert_eq!(center.move_dir(DownRight).unwrap().coord(), (2, 2));
+        assert_eq!(center.move_dir(Offset(1, -1)).unwrap().coord(), (2, 0));
+        assert_eq!(center.move_dir(Offset(2, 0)).map(|c| c.coord()), None);
+
+        assert_eq!(corner.move_dir(Up).map(|c| c.coord()), None);
+        assert_eq!(corner.move_dir(DownLeft).map(|c| c.coord()), None);
+        assert_eq!(corner.move_dir(Offset(1, 2)).unwrap().coord(), (1, 2));
     }
 
     #[test]
     fn name() {
-        let map: Map = "# \n0".parse().unwrap();
+        let mut map: Map = "# \n0".parse().unwrap();
         let coord = map.cursor().coord();
         map.write(coord, Sea);
     }
+
+    #[test]
+    fn grid_cursor() {
+        let grid = Grid::new(2, 2, vec![1u8, 2, 3, 4]).unwrap();
+        let cells: Vec<u8> = grid.cursor().iter().map(|c| c.read()).collect();
+
+        assert_eq!(cells, [1, 2, 3, 4]);
+        assert_eq!(grid.cursor().get((1, 1)).map(|c| c.cell()), Some(&4));
+        assert_eq!(Grid::<u8>::filled(0, 0, 0).cursor().iter().count(), 0);
+    }
 }
@@ -0,0 +1,420 @@
+use super::error::{Error, ErrorList};
+use std::collections::BTreeMap;
+
+/// ANSI styles used in colour mode
+mod style {
+    pub const ERROR: &str = "\x1b[1;31m";
+    pub const WARNING: &str = "\x1b[1;33m";
+    pub const GUTTER: &str = "\x1b[1;34m";
+    pub const MESSAGE: &str = "\x1b[1m";
+    pub const RESET: &str = "\x1b[0m";
+}
+
+/// Severity of the rendered errors
+#[derive(Debug, Clone, Copy)]
+enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    }
+
+    fn style(self) -> &'static str {
+        match self {
+            Level::Error => style::ERROR,
+            Level::Warning => style::WARNING,
+        }
+    }
+}
+
+/// # Render an [`ErrorList`] as rustc-style diagnostics
+///
+/// Errors are grouped by line, each group prints the offending line of the raw map with its number
+/// in a gutter, and a caret under every column holding an error:
+///
+/// ```text
+/// error: 2 errors on line 6
+///  --> map.txt:6:4
+///   |
+/// 6 | ## [##]
+///   |    ^  ^ invalid character ']'
+///   |    |
+///   |    invalid character '['
+/// ```
+///
+/// Errors without a column point right after the end of their line, errors without a line are
+/// printed alone. Colours are off by default, see [`Diagnostics::colour`]. The errors repaired by
+/// a lenient parsing are rendered the same way with [`Diagnostics::render_warnings`].
+#[derive(Debug, Clone)]
+pub struct Diagnostics<'a> {
+    source: &'a str,
+    origin: Option<&'a str>,
+    colour: bool,
+}
+
+impl<'a> Diagnostics<'a> {
+    /// Diagnostics of errors found in the raw map `source`
+    pub fn new(source: &'a str) -> Self {
+        Diagnostics {
+            source,
+            origin: None,
+            colour: false,
+        }
+    }
+
+    /// Name of the raw map, e.g. its file path, written before the positions
+    pub fn origin(mut self, origin: &'a str) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Use ANSI colours, `false` gives a plain output for logs
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    pub fn render(&self, errors: &ErrorList) -> String {
+        self.render_as(errors, Level::Error)
+    }
+
+    /// Same as [`Diagnostics::render`] with `warning` headlines, e.g. for the errors repaired by
+    /// [`ParseOptions::lenient`](super::ParseOptions::lenient)
+    pub fn render_warnings(&self, warnings: &ErrorList) -> String {
+        self.render_as(warnings, Level::Warning)
+    }
+
+    fn render_as(&self, errors: &ErrorList, level: Level) -> String {
+        let ErrorList(errors) = errors;
+        let mut lines: BTreeMap<usize, Vec<(usize, &Error)>> = BTreeMap::new();
+        let mut output = String::new();
+
+        for error in errors {
+            match position(error) {
+                Some((line, col)) => {
+                    let col = col.unwrap_or_else(|| self.line(line).chars().count() + 1);
+                    lines.entry(line).or_default().push((col, error));
+                }
+                None => output += &self.headline(level, &error.to_string()),
+            }
+        }
+
+        let gutter_width = lines.keys().last().map_or(0, |line| line.to_string().len());
+
+        for (line, mut line_errors) in lines {
+            line_errors.sort_by_key(|&(col, _)| col);
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output += &self.snippet(level, line, &line_errors, gutter_width);
+        }
+
+        if errors.len() > 1 {
+            let summary = match level {
+                Level::Error => format!("aborting due to {} errors", errors.len()),
+                Level::Warning => format!("{} warnings emitted", errors.len()),
+            };
+            output += &format!("\n{}", self.headline(level, &summary));
+        }
+
+        output
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.source.lines().nth(line - 1).unwrap_or("")
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        match self.colour {
+            true => format!("{}{}{}", style, text, style::RESET),
+            false => text.to_string(),
+        }
+    }
+
+    fn headline(&self, level: Level, message: &str) -> String {
+        format!(
+            "{}{}\n",
+            self.paint(level.style(), level.name()),
+            self.paint(style::MESSAGE, &format!(": {}", message))
+        )
+    }
+
+    fn snippet(
+        &self,
+        level: Level,
+        line: usize,
+        errors: &[(usize, &Error)],
+        gutter_width: usize,
+    ) -> String {
+        let message = match errors {
+            [(_, error)] => error.to_string(),
+            _ => format!("{} errors on line {}", errors.len(), line),
+        };
+        let location = match self.origin {
+            Some(origin) => format!("{}:{}:{}", origin, line, errors[0].0),
+            None => format!("{}:{}", line, errors[0].0),
+        };
+        let gutter = |number: &str| {
+            let gutter = format!("{:>width$} |", number, width = gutter_width);
+            self.paint(style::GUTTER, &gutter)
+        };
+
+        let mut output = self.headline(level, &message);
+        let arrow = format!("{:>width$}", "-->", width = gutter_width + 3);
+        output += &format!("{} {}\n", self.paint(style::GUTTER, &arrow), location);
+        output += &format!("{}\n", gutter(""));
+        output += &match self.line(line) {
+            "" => format!("{}\n", gutter(&line.to_string())),
+            text => format!("{} {}\n", gutter(&line.to_string()), text),
+        };
+
+        // carets, the last error being labelled on the same line
+        let cols: Vec<usize> = errors.iter().map(|&(col, _)| col).collect();
+        let (_, last_error) = errors[errors.len() - 1];
+        let carets = format!("{} {}", marker_line(&cols, '^'), label(last_error));
+        output += &format!("{} {}\n", gutter(""), self.paint(level.style(), &carets));
+
+        // the other errors, right to left, hanging below their caret
+        for i in (0..errors.len() - 1).rev() {
+            output += &format!("{} {}\n", gutter(""), marker_line(&cols[..=i], '|'));
+            let pipes = marker_line(&cols[..i], '|');
+            let padding = " ".repeat((cols[i] - 1).saturating_sub(pipes.chars().count()));
+            output += &format!(
+                "{} {}{}{}\n",
+                gutter(""),
+                pipes,
+                padding,
+                self.paint(level.style(), &label(errors[i].1))
+            );
+        }
+
+        output
+    }
+}
+
+/// `marker` at every 1-based column of the sorted `cols`, spaces in between
+fn marker_line(cols: &[usize], marker: char) -> String {
+    let mut line = String::new();
+
+    for &col in cols {
+        let len = line.chars().count();
+        if col > len {
+            line += &" ".repeat(col - 1 - len);
+            line.push(marker);
+        }
+    }
+
+    line
+}
+
+/// 1-based line and column of an error, when it has them
+fn position(error: &Error) -> Option<(usize, Option<usize>)> {
+    match *error {
+        Error::EmptyMap => None,
+        Error::EmptyLine(line) => Some((line, None)),
+        Error::RaggedLine { line, .. } => Some((line, None)),
+        Error::InvalidChar { line, col, .. }
+        | Error::MixedLabels { line, col, .. }
+        | Error::DuplicateLabel { line, col, .. }
+        | Error::UnlabelledEarth { line, col } => Some((line, Some(col))),
+    }
+}
+
+/// Short message written under the caret of an error
+fn label(error: &Error) -> String {
+    match *error {
+        Error::EmptyMap => "map is empty".to_string(),
+        Error::EmptyLine(_) => "empty line".to_string(),
+        Error::InvalidChar { char, .. } => format!("invalid character '{}'", char),
+        Error::RaggedLine {
+            expected, found, ..
+        } => format!("{} cells but {} were expected", found, expected),
+        Error::MixedLabels { label, other, .. } => {
+            format!("island {} also labelled {}", label, other)
+        }
+        Error::DuplicateLabel { label, .. } => {
+            format!("label {} used by another island", label)
+        }
+        Error::UnlabelledEarth { .. } => "unlabelled earth".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW_MAP: &str = "\
+## [##]
+#
+
+  #!
+";
+
+    fn invalid_char(char: char, line: usize, col: usize) -> Error {
+        Error::InvalidChar { char, line, col }
+    }
+
+    #[test]
+    fn single_error() {
+        let errors = ErrorList(vec![invalid_char('!', 4, 4)]);
+
+        assert_eq!(
+            Diagnostics::new(RAW_MAP).origin("map.txt").render(&errors),
+            "\
+error: invalid character '!' found at position 4:4
+ --> map.txt:4:4
+  |
+4 |   #!
+  |    ^ invalid character '!'
+"
+        );
+    }
+
+    #[test]
+    fn grouped_errors() {
+        let errors = ErrorList(vec![
+            invalid_char('[', 1, 4),
+            invalid_char(']', 1, 7),
+            Error::EmptyLine(3),
+        ]);
+
+        assert_eq!(
+            Diagnostics::new(RAW_MAP).render(&errors),
+            "\
+error: 2 errors on line 1
+ --> 1:4
+  |
+1 | ## [##]
+  |    ^  ^ invalid character ']'
+  |    |
+  |    invalid character '['
+
+error: empty line found at line '3'
+ --> 3:1
+  |
+3 |
+  | ^ empty line
+
+error: aborting due to 3 errors
+"
+        );
+    }
+
+    #[test]
+    fn three_errors_on_a_line() {
+        let errors = ErrorList(vec![
+            invalid_char('a', 1, 1),
+            invalid_char('b', 1, 3),
+            invalid_char('c', 1, 5),
+        ]);
+        let output = Diagnostics::new("a b c\n").render(&errors);
+
+        assert!(output.contains(
+            "\
+  | ^ ^ ^ invalid character 'c'
+  | | |
+  | | invalid character 'b'
+  | |
+  | invalid character 'a'
+"
+        ));
+    }
+
+    #[test]
+    fn ragged_line_points_after_the_end() {
+        let errors = ErrorList(vec![Error::RaggedLine {
+            line: 2,
+            expected: 3,
+            found: 1,
+        }]);
+        let output = Diagnostics::new("###\n#\n").render(&errors);
+
+        assert!(output.contains("2 | #\n  |  ^ 1 cells but 3 were expected\n"));
+    }
+
+    #[test]
+    fn wide_gutter() {
+        let raw_map = "#\n".repeat(9) + "#!\n";
+        let errors = ErrorList(vec![invalid_char('!', 10, 2)]);
+
+        assert!(Diagnostics::new(&raw_map)
+            .render(&errors)
+            .contains("  --> 10:2\n   |\n10 | #!\n   |  ^"));
+    }
+
+    #[test]
+    fn error_without_line() {
+        let errors = ErrorList(vec![Error::EmptyMap]);
+        assert_eq!(
+            Diagnostics::new("").render(&errors),
+            "error: map is empty\n"
+        );
+    }
+
+    #[test]
+    fn same_column_twice() {
+        let errors = ErrorList(vec![
+            Error::UnlabelledEarth { line: 1, col: 2 },
+            Error::UnlabelledEarth { line: 1, col: 2 },
+        ]);
+
+        assert!(Diagnostics::new("##\n")
+            .render(&errors)
+            .contains("  |  ^ unlabelled earth\n  |  |\n  |  unlabelled earth\n"));
+    }
+
+    #[test]
+    fn labels_past_the_default_marks() {
+        let errors = ErrorList(vec![Error::MixedLabels {
+            label: 40,
+            other: 7,
+            line: 1,
+            col: 2,
+        }]);
+
+        assert!(Diagnostics::new("##\n")
+            .render(&errors)
+            .contains("  |  ^ island 40 also labelled 7\n"));
+    }
+
+    #[test]
+    fn warnings() {
+        let warnings = ErrorList(vec![invalid_char('!', 4, 4), Error::EmptyLine(3)]);
+
+        assert_eq!(
+            Diagnostics::new(RAW_MAP).render_warnings(&warnings),
+            "\
+warning: empty line found at line '3'
+ --> 3:1
+  |
+3 |
+  | ^ empty line
+
+warning: invalid character '!' found at position 4:4
+ --> 4:4
+  |
+4 |   #!
+  |    ^ invalid character '!'
+
+warning: 2 warnings emitted
+"
+        );
+    }
+
+    #[test]
+    fn colour() {
+        let errors = ErrorList(vec![Error::EmptyMap]);
+        let output = Diagnostics::new("").colour(true).render(&errors);
+
+        assert_eq!(
+            output,
+            "\x1b[1;31merror\x1b[0m\x1b[1m: map is empty\x1b[0m\n"
+        );
+    }
+}
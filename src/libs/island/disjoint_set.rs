@@ -0,0 +1,123 @@
+/// # Disjoint-set forest
+///
+/// Sets are identified by `usize` elements created with [`DisjointSet::make_set`],
+/// uses union by rank and path halving.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisjointSet {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+    set_count: usize,
+}
+
+impl DisjointSet {
+    pub fn new() -> Self {
+        DisjointSet::default()
+    }
+
+    /// Number of elements (not sets)
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Number of disjoint sets
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    /// Add a new singleton set and get its element
+    pub fn make_set(&mut self) -> usize {
+        let element = self.parents.len();
+        self.parents.push(element);
+        self.ranks.push(0);
+        self.set_count += 1;
+        element
+    }
+
+    /// Representative element of the set holding `element`
+    pub fn find(&mut self, element: usize) -> usize {
+        let mut element = element;
+
+        while self.parents[element] != element {
+            let grand_parent = self.parents[self.parents[element]];
+            self.parents[element] = grand_parent;
+            element = grand_parent;
+        }
+
+        element
+    }
+
+    /// Merge the sets holding `a` and `b` and get the representative of the merged set
+    pub fn union(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+
+        let (parent, child) = if self.ranks[a] < self.ranks[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+
+        self.parents[child] = parent;
+        self.set_count -= 1;
+        if self.ranks[parent] == self.ranks[child] {
+            self.ranks[parent] += 1;
+        }
+
+        parent
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn singletons() {
+        let mut sets = DisjointSet::new();
+        let (a, b) = (sets.make_set(), sets.make_set());
+
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets.set_count(), 2);
+        assert_eq!(sets.find(a), a);
+        assert_eq!(sets.find(b), b);
+        assert!(!sets.same(a, b));
+    }
+
+    #[test]
+    fn union() {
+        let mut sets = DisjointSet::new();
+        let elements: Vec<usize> = (0..6).map(|_| sets.make_set()).collect();
+
+        sets.union(elements[0], elements[1]);
+        sets.union(elements[2], elements[3]);
+        let root = sets.union(elements[1], elements[3]);
+
+        assert!(sets.same(elements[0], elements[2]));
+        assert_eq!(sets.find(elements[3]), root);
+        assert!(!sets.same(elements[0], elements[4]));
+        assert_eq!(sets.union(elements[0], elements[3]), root);
+        assert_eq!(sets.set_count(), 3);
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut sets = DisjointSet::new();
+        let elements: Vec<usize> = (0..100_000).map(|_| sets.make_set()).collect();
+
+        for pair in elements.windows(2) {
+            sets.union(pair[0], pair[1]);
+        }
+
+        assert!(sets.same(elements[0], elements[99_999]));
+    }
+}
@@ -0,0 +1,426 @@
+use super::cell::{Cell, CellKind};
+use super::connectivity::Connectivity;
+use super::disjoint_set::DisjointSet;
+use super::grid::Grid;
+use super::label::Label;
+use super::map::Map;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// # A map whose islands are kept labelled while its cells are edited
+///
+/// Turning a sea cell to earth unions it with the islands around it in a [`DisjointSet`].
+/// Turning an earth cell to sea may split its island: the earth around the cell is explored from
+/// every neighbour at once, one cell each in turn, until every neighbour is known to reach the
+/// others or a piece is entirely explored. Only the split pieces, not the largest one, are
+/// explored in full and moved to new sets.
+///
+/// Marks of the map are read as earth. The map is readable through `Deref`, edits go through
+/// [`DynamicMap::write`].
+///
+/// ```
+/// # use garm_playground::libs::island::{Cell, Connectivity, DynamicMap, ParseOptions};
+/// let (map, _) = ParseOptions::new().parse("###\n").unwrap();
+/// let mut map = DynamicMap::new(map, Connectivity::four());
+/// assert_eq!(map.island_count(), 1);
+///
+/// map.write((1, 0), Cell::Sea);
+/// assert_eq!(map.island_count(), 2);
+/// assert!(!map.same_island((0, 0), (2, 0)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynamicMap {
+    map: Map,
+    connectivity: Connectivity,
+    sets: DisjointSet,
+    /// element of every earth cell in `sets`
+    set_of: Grid<Option<usize>>,
+    island_count: usize,
+}
+
+impl DynamicMap {
+    pub fn new(map: Map, connectivity: Connectivity) -> Self {
+        let set_of = Grid::filled(map.width(), map.height(), None);
+        let mut map = DynamicMap {
+            map,
+            connectivity,
+            sets: DisjointSet::new(),
+            set_of,
+            island_count: 0,
+        };
+
+        map.map.unmark();
+        map.rebuild();
+        map
+    }
+
+    pub fn island_count(&self) -> usize {
+        self.island_count
+    }
+
+    /// Identifier shared by every cell of the island at `coord`, `None` for sea and outside
+    /// of the map
+    ///
+    /// Identifiers are only stable until the next edit, see [`DynamicMap::labelled`] for labels.
+    pub fn island(&mut self, coord: (usize, usize)) -> Option<usize> {
+        let element = self.set_of.get(coord)??;
+        Some(self.sets.find(element))
+    }
+
+    /// `true` when both cells are earth of the same island
+    pub fn same_island(&mut self, a: (usize, usize), b: (usize, usize)) -> bool {
+        match (self.island(a), self.island(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Set the cell at `coord`, `None` when it is outside of the map
+    ///
+    /// Marks are written as earth.
+    pub fn write(&mut self, coord: (usize, usize), cell: Cell) -> Option<()> {
+        let earth = self.map.get(coord)? != Cell::Sea;
+
+        match cell {
+            Cell::Sea if earth => self.remove(coord),
+            Cell::Earth | Cell::MarkedEarth(_) if !earth => self.add(coord),
+            _ => {}
+        }
+
+        // every edit adds an element, split pieces leave their former ones behind
+        if self.sets.len() > 2 * self.set_of.cells().len() {
+            self.rebuild();
+        }
+        Some(())
+    }
+
+    /// Turn sea to earth and earth to sea, `None` when `coord` is outside of the map
+    pub fn toggle(&mut self, coord: (usize, usize)) -> Option<()> {
+        match self.map.get(coord)? {
+            Cell::Sea => self.write(coord, Cell::Earth),
+            _ => self.write(coord, Cell::Sea),
+        }
+    }
+
+    /// The map labelled as by [`FloodFill`](super::FloodFill), islands being numbered in the
+    /// reading order of their first cell
+    pub fn labelled(&mut self) -> Map {
+        let mut map = self.map.clone();
+        let mut island_labels: HashMap<usize, Label> = HashMap::new();
+        let mut labels = Grid::filled(map.width(), map.height(), None);
+
+        for (label, element) in labels.cells_mut().iter_mut().zip(self.set_of.cells()) {
+            if let Some(element) = element {
+                let island = self.sets.find(*element);
+                let next_label = island_labels.len();
+                *label = Some(*island_labels.entry(island).or_insert(next_label));
+            }
+        }
+
+        map.mark(&labels);
+        map
+    }
+
+    /// The unlabelled map
+    pub fn into_map(self) -> Map {
+        self.map
+    }
+
+    fn add(&mut self, coord: (usize, usize)) {
+        let element = self.sets.make_set();
+        self.map[coord] = CellKind::Earth;
+        self.set_of[coord] = Some(element);
+        self.island_count += 1;
+
+        for neighbour in self.neighbours(coord) {
+            let island = self.set_of[neighbour].expect("[FATAL] earth neighbour");
+            if !self.sets.same(element, island) {
+                self.sets.union(element, island);
+                self.island_count -= 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, coord: (usize, usize)) {
+        let neighbours = self.neighbours(coord);
+        self.map[coord] = CellKind::Sea;
+        self.set_of[coord] = None;
+
+        if neighbours.is_empty() {
+            self.island_count -= 1;
+            return;
+        }
+
+        let pieces = self.explore(&neighbours);
+        self.island_count += pieces.len();
+
+        for piece in pieces {
+            let element = self.sets.make_set();
+            for cell in piece {
+                self.set_of[cell] = Some(element);
+            }
+        }
+    }
+
+    /// Explore the earth around a removed cell from its `neighbours`, one search each, and get
+    /// the pieces split from the last search left
+    fn explore(&self, neighbours: &[(usize, usize)]) -> Vec<Vec<(usize, usize)>> {
+        // searches meeting are merged, a group of searches sharing a single frontier
+        let mut groups = DisjointSet::new();
+        let mut frontiers: Vec<Vec<(usize, usize)>> = vec![];
+        let mut visited: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for &neighbour in neighbours {
+            let search = groups.make_set();
+            frontiers.push(vec![neighbour]);
+            visited.insert(neighbour, search);
+        }
+
+        loop {
+            let active: Vec<usize> = (0..frontiers.len())
+                .filter(|&search| groups.find(search) == search && !frontiers[search].is_empty())
+                .collect();
+            if active.len() <= 1 {
+                break;
+            }
+
+            for search in active {
+                let mut group = groups.find(search);
+                let Some(cell) = frontiers[group].pop() else {
+                    continue;
+                };
+
+                for neighbour in self.neighbours(cell) {
+                    let Some(&other) = visited.get(&neighbour) else {
+                        visited.insert(neighbour, group);
+                        frontiers[group].push(neighbour);
+                        continue;
+                    };
+
+                    let other = groups.find(other);
+                    if other != group {
+                        let merged = groups.union(group, other);
+                        let child = if merged == group { other } else { group };
+                        let frontier = std::mem::take(&mut frontiers[child]);
+                        frontiers[merged].extend(frontier);
+                        group = merged;
+                    }
+                }
+            }
+        }
+
+        // every group left without a frontier is a whole piece, the last one keeps the island
+        let mut pieces: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        for (cell, search) in visited {
+            let group = groups.find(search);
+            if frontiers[group].is_empty() {
+                pieces.entry(group).or_default().push(cell);
+            }
+        }
+        if groups.set_count() == pieces.len() {
+            let kept = groups.find(0);
+            pieces.remove(&kept);
+        }
+
+        pieces.into_values().collect()
+    }
+
+    /// Earth cells connected to `coord`
+    fn neighbours(&self, coord: (usize, usize)) -> Vec<(usize, usize)> {
+        let cursor = self
+            .map
+            .cursor()
+            .get(coord)
+            .expect("[FATAL] cell of the map");
+
+        self.connectivity
+            .directions()
+            .iter()
+            .filter_map(|&dir| cursor.move_dir(dir))
+            .filter(|neighbour| neighbour.read() != Cell::Sea)
+            .map(|neighbour| neighbour.coord())
+            .collect()
+    }
+
+    /// Union every earth cell from scratch, in reading order
+    fn rebuild(&mut self) {
+        self.sets = DisjointSet::new();
+        self.set_of = Grid::filled(self.map.width(), self.map.height(), None);
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                if self.map[(x, y)] == CellKind::Sea {
+                    continue;
+                }
+
+                let element = self.sets.make_set();
+                self.set_of[(x, y)] = Some(element);
+                for neighbour in self.neighbours((x, y)) {
+                    if let Some(island) = self.set_of[neighbour] {
+                        self.sets.union(element, island);
+                    }
+                }
+            }
+        }
+
+        self.island_count = self.sets.set_count();
+    }
+}
+
+impl Deref for DynamicMap {
+    type Target = Map;
+
+    fn deref(&self) -> &Map {
+        &self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::generator::Random;
+    use crate::libs::island::labeler::{FloodFill, Labeler};
+    use crate::libs::island::map::ParseOptions;
+
+    const RING: &str = "\
+#####
+#   #
+#####
+";
+
+    fn dynamic_map(raw: &str, connectivity: Connectivity) -> DynamicMap {
+        let (map, _) = ParseOptions::new().parse(raw).unwrap();
+        DynamicMap::new(map, connectivity)
+    }
+
+    /// Fail when the dynamic map is not labelled as the same map labelled from scratch
+    fn assert_labelled(map: &mut DynamicMap, connectivity: &Connectivity) {
+        let expected = FloodFill.label(map.map.clone(), connectivity);
+        let island_count = expected.cells().filter_map(|cell| match cell {
+            Cell::MarkedEarth(label) => Some(label + 1),
+            _ => None,
+        });
+
+        assert_eq!(map.labelled(), expected);
+        assert_eq!(map.island_count(), island_count.max().unwrap_or(0));
+    }
+
+    #[test]
+    fn add() {
+        let mut map = dynamic_map("# #\n   \n# #\n", Connectivity::four());
+        assert_eq!(map.island_count(), 4);
+
+        map.write((1, 0), Cell::Earth);
+        assert_eq!(map.island_count(), 3);
+        map.write((1, 1), Cell::Earth);
+        assert_eq!(map.island_count(), 3);
+        map.write((1, 2), Cell::MarkedEarth(7));
+        assert_eq!(map.island_count(), 1);
+        assert_eq!(map.to_string(), "###\n # \n###\n");
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = dynamic_map(RING, Connectivity::four());
+
+        map.write((2, 0), Cell::Sea);
+        assert_eq!(map.island_count(), 1);
+        map.write((2, 2), Cell::Sea);
+        assert_eq!(map.island_count(), 2);
+        assert!(map.same_island((0, 0), (1, 2)));
+        assert!(!map.same_island((0, 0), (4, 0)));
+
+        map.write((4, 1), Cell::Sea);
+        assert_eq!(map.island_count(), 3);
+        map.write((4, 0), Cell::Sea);
+        map.write((4, 2), Cell::Sea);
+        map.write((3, 0), Cell::Sea);
+        assert_eq!(map.island_count(), 2);
+        map.write((3, 2), Cell::Sea);
+        assert_eq!(map.island_count(), 1);
+    }
+
+    #[test]
+    fn split_in_several_pieces() {
+        let mut map = dynamic_map(" # \n###\n # \n", Connectivity::four());
+
+        map.write((1, 1), Cell::Sea);
+        assert_eq!(map.island_count(), 4);
+        assert_labelled(&mut map, &Connectivity::four());
+    }
+
+    #[test]
+    fn connectivity() {
+        let mut map = dynamic_map("# \n #\n", Connectivity::eight());
+        assert_eq!(map.island_count(), 1);
+
+        map.write((1, 0), Cell::Earth);
+        map.write((0, 0), Cell::Sea);
+        assert_eq!(map.island_count(), 1);
+        map.write((1, 0), Cell::Sea);
+        assert_eq!(map.island_count(), 1);
+        map.write((1, 1), Cell::Sea);
+        assert_eq!(map.island_count(), 0);
+    }
+
+    #[test]
+    fn island() {
+        let mut map = dynamic_map("#0 \n  #\n", Connectivity::four());
+
+        assert_eq!(map.island_count(), 2);
+        assert_eq!(map.island((0, 0)), map.island((1, 0)));
+        assert_eq!(map.island((2, 0)), None);
+        assert_eq!(map.island((3, 0)), None);
+        assert_ne!(map.island((2, 1)), None);
+    }
+
+    #[test]
+    fn outside() {
+        let mut map = dynamic_map("#\n", Connectivity::four());
+
+        assert_eq!(map.write((1, 0), Cell::Earth), None);
+        assert_eq!(map.toggle((0, 1)), None);
+        assert_eq!(map.island_count(), 1);
+    }
+
+    #[test]
+    fn toggle() {
+        let mut map = dynamic_map(RING, Connectivity::four());
+
+        for _ in 0..100 {
+            map.toggle((2, 1));
+            map.toggle((2, 2));
+        }
+        assert_eq!(map.island_count(), 1);
+        assert!(map.sets.len() <= 2 * 15);
+
+        map.toggle((2, 0));
+        map.toggle((2, 2));
+        assert_eq!(map.island_count(), 2);
+        assert_labelled(&mut map, &Connectivity::four());
+    }
+
+    #[test]
+    fn random_edits() {
+        for connectivity in [
+            Connectivity::four(),
+            Connectivity::eight(),
+            Connectivity::knight(),
+        ] {
+            let mut random = Random::new(3);
+            let cells = (0..30 * 20)
+                .map(|_| match random.chance(0.55) {
+                    true => Cell::Earth,
+                    false => Cell::Sea,
+                })
+                .collect();
+            let grid = Grid::new(30, 20, cells).unwrap();
+            let mut map = DynamicMap::new(Map::from(grid), connectivity.clone());
+
+            for _ in 0..1000 {
+                map.toggle((random.below(30), random.below(20)));
+                assert_labelled(&mut map, &connectivity);
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::label::Label;
 use std::fmt;
 
 /// # A count_island error
@@ -5,13 +6,46 @@ use std::fmt;
 /// - `EmptyMap`: an empty string "" has been parsed
 /// - `EmptyLine`: `usize` is the line number
 /// - `InvalidChar`: a not allowed char is present at line:col
+/// - `RaggedLine`: with [`RaggedLines::Strict`](super::RaggedLines::Strict), a line has not the
+///   length of the first line
+///
+/// Errors found when verifying an already labelled map, see [`verify`](super::verify()):
+///
+/// - `MixedLabels`: an island labelled `label` also uses `other` at line:col
+/// - `DuplicateLabel`: `label` is used by another island, found at line:col
+/// - `UnlabelledEarth`: an earth cell is not labelled at line:col
 ///
 /// valid chars are `'#'` | `' '` | `'0'..'z'`
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     EmptyMap,
     EmptyLine(usize),
-    InvalidChar { char: char, line: usize, col: usize },
+    InvalidChar {
+        char: char,
+        line: usize,
+        col: usize,
+    },
+    RaggedLine {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    MixedLabels {
+        label: Label,
+        other: Label,
+        line: usize,
+        col: usize,
+    },
+    DuplicateLabel {
+        label: Label,
+        line: usize,
+        col: usize,
+    },
+    UnlabelledEarth {
+        line: usize,
+        col: usize,
+    },
 }
 
 use Error::*;
@@ -26,6 +60,33 @@ impl fmt::Display for Error {
                 "invalid character '{}' found at position {}:{}",
                 char, line, col
             ),
+            RaggedLine {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {} has {} cells but {} were expected",
+                line, found, expected
+            ),
+            MixedLabels {
+                label,
+                other,
+                line,
+                col,
+            } => write!(
+                f,
+                "island {} is also labelled {} at position {}:{}",
+                label, other, line, col
+            ),
+            DuplicateLabel { label, line, col } => write!(
+                f,
+                "label {} is reused by another island at position {}:{}",
+                label, line, col
+            ),
+            UnlabelledEarth { line, col } => {
+                write!(f, "unlabelled earth found at position {}:{}", line, col)
+            }
         }
     }
 }
@@ -42,6 +103,7 @@ impl std::error::Error for Error {}
 ///
 /// all errors are joined with a `\n` character
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ErrorList(pub Vec<Error>);
 
 impl fmt::Display for ErrorList {
@@ -86,6 +148,32 @@ mod tests {
             col: 42,
         };
         assert!(format!("{}", err).contains("21:42"));
+
+        let err = RaggedLine {
+            line: 3,
+            expected: 12,
+            found: 7,
+        };
+        assert!(format!("{}", err).contains("line 3 has 7 cells but 12"));
+
+        let err = MixedLabels {
+            label: 1,
+            other: 10,
+            line: 4,
+            col: 2,
+        };
+        assert!(format!("{}", err).contains("island 1 is also labelled 10 at position 4:2"));
+
+        // labels past the default marks are not rendered as earth
+        let err = DuplicateLabel {
+            label: 40,
+            line: 5,
+            col: 6,
+        };
+        assert!(format!("{}", err).contains("label 40 is reused by another island at position 5:6"));
+
+        let err = UnlabelledEarth { line: 7, col: 8 };
+        assert!(format!("{}", err).contains("7:8"));
     }
 
     #[test]
@@ -100,5 +188,25 @@ mod tests {
             col: 2,
         }
         .into();
+        _dynerr = RaggedLine {
+            line: 1,
+            expected: 2,
+            found: 3,
+        }
+        .into();
+        _dynerr = MixedLabels {
+            label: 0,
+            other: 1,
+            line: 2,
+            col: 3,
+        }
+        .into();
+        _dynerr = DuplicateLabel {
+            label: 0,
+            line: 1,
+            col: 2,
+        }
+        .into();
+        _dynerr = UnlabelledEarth { line: 1, col: 2 }.into();
     }
 }
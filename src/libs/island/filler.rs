@@ -1,62 +1,110 @@
-use crate::libs::island::{
-    // cell::constants::RADIX_BASE,
-    cell::*,
-    cursor::Cursor,
-    cursor::DIRECTIONS,
-    map::Map,
-};
-
-use Cell::*;
-
-// TODO errors: replace Option type with Result
-pub struct Filler {
-    pub map: Map,
-    cell: std::cell::Cell<Option<Cell>>,
+use crate::libs::island::{connectivity::Connectivity, grid::Grid, label::Label};
+
+/// # Flood fill of the regions of a [`Grid`]
+///
+/// Each [`Filler::fill`] labels the region of a cell with the next label, in a separate grid of
+/// labels. Neighbouring cells (see [`Connectivity`]) are in the same region when
+/// `same_region(cell, neighbour)` is `true`: any cell type can be labelled, e.g. islands of
+/// earth in a [`Map`](super::Map), or pixels of close grey levels in an image.
+///
+/// Cells not in the same region as themselves are background and never labelled.
+///
+/// ```
+/// # use garm_playground::libs::island::{Connectivity, Filler, Grid};
+/// let image = Grid::new(4, 1, vec![10u8, 12, 200, 11]).unwrap();
+/// let mut filler = Filler::new(&image, Connectivity::four(), |a: &u8, b: &u8| a.abs_diff(*b) < 8);
+///
+/// assert_eq!(filler.fill((0, 0)), Some(0));
+/// assert_eq!(filler.fill((1, 0)), None);
+/// assert_eq!(filler.fill((3, 0)), Some(1));
+/// assert_eq!(filler.labels().cells(), [Some(0), Some(0), None, Some(1)]);
+/// ```
+pub struct Filler<'a, T, F> {
+    grid: &'a Grid<T>,
+    labels: Grid<Option<Label>>,
+    label: Label,
+    connectivity: Connectivity,
+    same_region: F,
 }
 
-impl Filler {
-    pub fn new(map: Map) -> Self {
-        let cell = Some(MarkedEarth('0'));
+impl<'a, T, F> Filler<'a, T, F>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    pub fn new(grid: &'a Grid<T>, connectivity: Connectivity, same_region: F) -> Self {
         Filler {
-            map,
-            cell: std::cell::Cell::new(cell),
+            grid,
+            labels: Grid::filled(grid.width(), grid.height(), None),
+            label: 0,
+            connectivity,
+            same_region,
         }
     }
 
-    pub fn fill(&self, cursor: Cursor) -> Option<()> {
-        let cell = self.cell.get();
-        cell.and_then(|_| self.fill_cells(Some(cursor)))?;
+    /// Fill the region of `coord` with the next label and get it, `None` when the cell is outside
+    /// of the grid, background or already labelled
+    pub fn fill(&mut self, coord: (usize, usize)) -> Option<Label> {
+        if !self.grid.contains(coord) {
+            return None;
+        }
+        let cell = &self.grid[coord];
 
-        let mut cell = cell?;
-        let cell = cell.increment().and(Ok(cell)).ok();
+        if self.labels[coord].is_some() || !(self.same_region)(cell, cell) {
+            return None;
+        }
 
-        self.cell.set(cell);
+        let label = self.label;
+        self.fill_cells(coord, label);
+        self.label += 1;
 
-        Some(())
+        Some(label)
     }
 
-    fn fill_cells(&self, cursor: Option<Cursor>) -> Option<()> {
-        let cursor = cursor?;
-        let cell = self.cell.get()?;
+    /// Number of regions filled so far
+    pub fn region_count(&self) -> usize {
+        self.label
+    }
 
-        if !self.map.get(cursor.coord())?.is_markable() {
-            return None;
-        }
+    /// Label of every cell, `None` for the cells not filled yet
+    pub fn labels(&self) -> &Grid<Option<Label>> {
+        &self.labels
+    }
 
-        self.map.write(cursor.coord(), cell);
+    pub fn into_labels(self) -> Grid<Option<Label>> {
+        self.labels
+    }
 
-        for dir in DIRECTIONS {
-            let next_coord = self.map.cursor().get(cursor.coord())?.move_dir(dir);
-            self.fill_cells(next_coord);
+    // Cells are labelled when pushed on the frontier so each cell is visited once,
+    // memory use is bounded by the frontier size instead of the call depth. The frontier is a
+    // plain Vec: a linked list node per cell would cost more than the fill itself
+    fn fill_cells(&mut self, coord: (usize, usize), label: Label) {
+        let mut frontier = Vec::new();
+        self.labels[coord] = Some(label);
+        frontier.push(coord);
+
+        while let Some((x, y)) = frontier.pop() {
+            let cell = &self.grid[(x, y)];
+
+            for &dir in self.connectivity.directions() {
+                let (dx, dy) = dir.offset();
+                let next = match x.checked_add_signed(dx).zip(y.checked_add_signed(dy)) {
+                    Some(next) if self.grid.contains(next) => next,
+                    _ => continue,
+                };
+
+                if self.labels[next].is_none() && (self.same_region)(cell, &self.grid[next]) {
+                    self.labels[next] = Some(label);
+                    frontier.push(next);
+                }
+            }
         }
-
-        Some(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::libs::island::{cell::CellKind, map::Map};
 
     const SIMPLE_MAP: &str = "\
 ###
@@ -96,82 +144,160 @@ mod tests {
 0 1 2 3 4 5 6 7 8 9 a b c d e f g h i j k l m n o p q r s t u v w x y z #
 ";
 
+    fn island(kind: &CellKind, other: &CellKind) -> bool {
+        *kind == CellKind::Earth && *other == CellKind::Earth
+    }
+
+    /// The map with the labels filled so far
+    fn marked(map: &Map, labels: &Grid<Option<Label>>) -> String {
+        let mut map = map.clone();
+        map.mark(labels);
+        map.to_string()
+    }
+
     #[test]
     fn fill_simple_one() {
         let map: Map = "#\n".parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!("#\n", filler.map.to_string());
-        assert_eq!(Some(()), filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!("0\n", filler.map.to_string());
+        assert_eq!("#\n", marked(&map, filler.labels()));
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!("0\n", marked(&map, filler.labels()));
     }
 
     #[test]
     fn fill_simple() {
         let map: Map = SIMPLE_MAP.parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!(SIMPLE_MAP, filler.map.to_string());
-        assert_eq!(Some(()), filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!(RESOLVED_SIMPLE_MAP, filler.map.to_string());
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(RESOLVED_SIMPLE_MAP, marked(&map, filler.labels()));
     }
 
     #[test]
     fn fill_multiple() {
         let map: Map = COMPLEX_MAP.parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!(COMPLEX_MAP, filler.map.to_string());
-        assert_eq!(Some(()), filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!(Some(()), filler.fill(cursor.get((2, 2)).unwrap()));
-        assert_eq!(RESOLVED_COMPLEX_MAP, filler.map.to_string());
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(Some(1), filler.fill((2, 2)));
+        assert_eq!(RESOLVED_COMPLEX_MAP, marked(&map, filler.labels()));
+        assert_eq!(filler.region_count(), 2);
     }
 
     #[test]
     fn fill_sea() {
         let map: Map = COMPLEX_MAP.parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!(COMPLEX_MAP, filler.map.to_string());
-        assert_eq!(None, filler.fill(cursor.get((1, 1)).unwrap()));
-        assert_eq!(Some(()), filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!(None, filler.fill(cursor.get((1, 1)).unwrap()));
-        assert_eq!(Some(()), filler.fill(cursor.get((2, 2)).unwrap()));
-        assert_eq!(RESOLVED_COMPLEX_MAP, filler.map.to_string());
+        assert_eq!(None, filler.fill((1, 1)));
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(None, filler.fill((1, 1)));
+        assert_eq!(Some(1), filler.fill((2, 2)));
+        assert_eq!(RESOLVED_COMPLEX_MAP, marked(&map, filler.labels()));
     }
 
     #[test]
-    fn fill_marked_earth() {
+    fn fill_twice() {
         let map: Map = COMPLEX_MAP.parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!(COMPLEX_MAP, filler.map.to_string());
-        assert_eq!(Some(()), filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!(None, filler.fill(cursor.get((0, 0)).unwrap()));
-        assert_eq!(Some(()), filler.fill(cursor.get((2, 2)).unwrap()));
-        assert_eq!(None, filler.fill(cursor.get((2, 2)).unwrap()));
-        assert_eq!(RESOLVED_COMPLEX_MAP, filler.map.to_string());
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(None, filler.fill((0, 0)));
+        assert_eq!(Some(1), filler.fill((2, 2)));
+        assert_eq!(None, filler.fill((3, 3)));
+        assert_eq!(RESOLVED_COMPLEX_MAP, marked(&map, filler.labels()));
     }
 
     #[test]
-    fn fill_overflow() {
+    fn fill_marked_earth() {
+        let map: Map = "#0#\n".parse().unwrap();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
+
+        assert_eq!(None, filler.fill((1, 0)));
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(Some(1), filler.fill((2, 0)));
+    }
+
+    #[test]
+    fn fill_outside() {
+        let map: Map = "#\n".parse().unwrap();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
+
+        assert_eq!(None, filler.fill((1, 0)));
+        assert_eq!(filler.region_count(), 0);
+    }
+
+    #[test]
+    fn fill_eight_connectivity() {
+        let map: Map = "# #\n # \n#  \n".parse().unwrap();
+        let mut filler = Filler::new(map.kinds(), Connectivity::eight(), island);
+
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!("0 0\n 0 \n0  \n", marked(&map, filler.labels()));
+    }
+
+    #[test]
+    fn fill_knight_connectivity() {
+        let map: Map = "#  \n  #\n ##\n".parse().unwrap();
+        let mut filler = Filler::new(map.kinds(), Connectivity::knight(), island);
+
+        assert_eq!(Some(0), filler.fill((0, 0)));
+        assert_eq!(Some(1), filler.fill((2, 2)));
+        assert_eq!("0  \n  0\n 01\n", marked(&map, filler.labels()));
+    }
+
+    #[test]
+    fn fill_huge_island() {
+        let size = 2000;
+        let raw_map = ("#".repeat(size) + "\n").repeat(size);
+        let map: Map = raw_map.parse().unwrap();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
+
+        assert_eq!(Some(0), filler.fill((size / 2, size / 2)));
+        assert!(filler.labels().cells().iter().all(|&l| l == Some(0)));
+        assert_eq!(None, filler.fill((0, 0)));
+    }
+
+    #[test]
+    fn fill_beyond_radix_base() {
         let map: Map = ALL_ISLAND.parse().unwrap();
-        let filler = Filler::new(map);
-        let cursor = filler.map.cursor();
+        let mut filler = Filler::new(map.kinds(), Connectivity::four(), island);
 
-        assert_eq!(ALL_ISLAND, filler.map.to_string());
-        for x in (0..RADIX_BASE * 2).step_by(2) {
-            let x = x.try_into().unwrap();
-            assert_eq!(Some(()), filler.fill(cursor.get((x, 0)).unwrap()));
+        for x in (0..=36 * 2).step_by(2) {
+            assert_eq!(Some(x / 2), filler.fill((x, 0)));
         }
 
-        let x = ((RADIX_BASE) * 2).try_into().unwrap();
-        assert_eq!(None, filler.fill(cursor.get((x, 0)).unwrap()));
-        assert_eq!(RESOLVED_ALL_ISLAND, filler.map.to_string());
+        assert_eq!(filler.labels().get((36 * 2, 0)), Some(Some(36)));
+        assert_eq!(RESOLVED_ALL_ISLAND, marked(&map, filler.labels()));
+    }
+
+    #[test]
+    fn fill_equal_values() {
+        let grid = Grid::from_lines(
+            ["aab", "abb", "cca"]
+                .iter()
+                .map(|line| line.chars().collect())
+                .collect(),
+            ' ',
+        );
+        let mut filler = Filler::new(&grid, Connectivity::four(), |a: &char, b: &char| a == b);
+
+        for coord in [(0, 0), (2, 0), (0, 2), (2, 2)] {
+            filler.fill(coord);
+        }
+
+        let labels: Vec<Label> = filler.labels().cells().iter().flatten().copied().collect();
+        assert_eq!(labels, [0, 0, 1, 0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn fill_non_copy_cells() {
+        let words = ["sea", "sea", "land"].map(String::from).to_vec();
+        let grid = Grid::new(3, 1, words).unwrap();
+        let mut filler = Filler::new(&grid, Connectivity::four(), |a: &String, b| a == b);
+
+        assert_eq!(Some(0), filler.fill((1, 0)));
+        assert_eq!(filler.labels().cells(), [Some(0), Some(0), None]);
     }
 }
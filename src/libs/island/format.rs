@@ -0,0 +1,193 @@
+use super::cell::{error::Parse, Cell, EARTH_CHAR, SEA_CHAR};
+use super::label::Alphabet;
+
+/*
+ * Errors
+ */
+pub mod error {
+    use std::fmt;
+
+    /// A character given to several kinds of cells
+    #[derive(PartialEq)]
+    pub struct Conflict(pub char);
+
+    impl fmt::Display for Conflict {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let Conflict(c) = *self;
+            write!(f, "character '{}' is used for several kinds of cells", c)
+        }
+    }
+
+    impl fmt::Debug for Conflict {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self)
+        }
+    }
+
+    impl std::error::Error for Conflict {}
+}
+
+/*
+ * MapFormat
+ */
+
+/// # Characters of a raw map
+///
+/// - `sea`: character of [`Cell::Sea`]
+/// - `earth`: character of [`Cell::Earth`]
+/// - `marks`: characters of [`Cell::MarkedEarth`], the label being the position in the alphabet
+///
+/// The three never share a character. [`MapFormat::DEFAULT`] is `' '`, `'#'` and base36 marks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapFormat {
+    sea: char,
+    earth: char,
+    marks: Alphabet,
+}
+
+impl MapFormat {
+    /// `' '` for sea, `'#'` for earth, [`Alphabet::BASE36`] marks
+    pub const DEFAULT: MapFormat = MapFormat {
+        sea: SEA_CHAR,
+        earth: EARTH_CHAR,
+        marks: Alphabet::BASE36,
+    };
+
+    /// `'.'` for sea, `'X'` for earth, [`Alphabet::BASE36`] marks
+    pub const DOTS: MapFormat = MapFormat {
+        sea: '.',
+        earth: 'X',
+        marks: Alphabet::BASE36,
+    };
+
+    /// `'0'` for sea, `'1'` for earth, no marks
+    pub const BINARY: MapFormat = MapFormat {
+        sea: '0',
+        earth: '1',
+        marks: Alphabet::NONE,
+    };
+
+    pub fn new(sea: char, earth: char, marks: Alphabet) -> Result<Self, error::Conflict> {
+        if sea == earth {
+            return Err(error::Conflict(sea));
+        }
+        if let Some(c) = [sea, earth].into_iter().find(|&c| marks.label(c).is_some()) {
+            return Err(error::Conflict(c));
+        }
+
+        Ok(MapFormat { sea, earth, marks })
+    }
+
+    pub fn sea(&self) -> char {
+        self.sea
+    }
+
+    pub fn earth(&self) -> char {
+        self.earth
+    }
+
+    pub fn marks(&self) -> Alphabet {
+        self.marks
+    }
+
+    /// Parse a single character of a raw map
+    pub fn cell(&self, c: char) -> Result<Cell, Parse> {
+        match c {
+            _ if c == self.sea => Ok(Cell::Sea),
+            _ if c == self.earth => Ok(Cell::Earth),
+            _ => self.marks.label(c).map(Cell::MarkedEarth).ok_or(Parse(c)),
+        }
+    }
+
+    /// Character of a cell, labels missing from `marks` are rendered as earth
+    pub fn char(&self, cell: Cell) -> char {
+        match cell {
+            Cell::Sea => self.sea,
+            Cell::Earth => self.earth,
+            Cell::MarkedEarth(label) => self.marks.char(label).unwrap_or(self.earth),
+        }
+    }
+}
+
+impl Default for MapFormat {
+    fn default() -> Self {
+        MapFormat::DEFAULT
+    }
+}
+
+/// Names accepted by [`map_format`]
+pub const MAP_FORMAT_NAMES: [&str; 3] = ["default", "dots", "binary"];
+
+/// Get a preset [`MapFormat`] from its name (see [`MAP_FORMAT_NAMES`])
+pub fn map_format(name: &str) -> Option<MapFormat> {
+    match name {
+        "default" => Some(MapFormat::DEFAULT),
+        "dots" => Some(MapFormat::DOTS),
+        "binary" => Some(MapFormat::BINARY),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for name in MAP_FORMAT_NAMES {
+            let format = map_format(name).unwrap();
+            assert_eq!(
+                MapFormat::new(format.sea(), format.earth(), format.marks()),
+                Ok(format)
+            );
+        }
+        assert!(map_format("ascii").is_none());
+    }
+
+    #[test]
+    fn conflicts() {
+        assert_eq!(
+            MapFormat::new('#', '#', Alphabet::NONE),
+            Err(error::Conflict('#'))
+        );
+        assert_eq!(
+            MapFormat::new('0', '1', Alphabet::BASE36),
+            Err(error::Conflict('0'))
+        );
+        assert_eq!(
+            MapFormat::new('.', 'a', Alphabet::BASE36),
+            Err(error::Conflict('a'))
+        );
+    }
+
+    #[test]
+    fn cell() {
+        let format = MapFormat::DOTS;
+
+        assert_eq!(format.cell('.'), Ok(Cell::Sea));
+        assert_eq!(format.cell('X'), Ok(Cell::Earth));
+        assert_eq!(format.cell('z'), Ok(Cell::MarkedEarth(35)));
+        assert_eq!(format.cell('#'), Err(Parse('#')));
+        assert_eq!(MapFormat::BINARY.cell('2'), Err(Parse('2')));
+    }
+
+    #[test]
+    fn char() {
+        let format = MapFormat::new('~', '^', Alphabet::GREEK).unwrap();
+
+        assert_eq!(format.char(Cell::Sea), '~');
+        assert_eq!(format.char(Cell::Earth), '^');
+        assert_eq!(format.char(Cell::MarkedEarth(0)), 'α');
+        assert_eq!(format.char(Cell::MarkedEarth(25)), '^');
+        assert_eq!(MapFormat::BINARY.char(Cell::MarkedEarth(0)), '1');
+    }
+
+    #[test]
+    fn round_trip() {
+        let format = MapFormat::new('.', '@', Alphabet::BASE62).unwrap();
+
+        for c in ['.', '@', '0', 'z', 'Z'] {
+            assert_eq!(format.char(format.cell(c).unwrap()), c);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use super::cell::Cell;
+use super::grid::Grid;
+use super::map::Map;
+
+/*
+ * Random
+ */
+
+/// # SplitMix64 pseudo-random generator
+///
+/// Small and fast, the same seed always giving the same sequence on every platform
+#[derive(Debug, Clone)]
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let Random(state) = self;
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, n)`, `n` must not be `0`
+    pub fn below(&mut self, n: usize) -> usize {
+        // the bias is negligible for the small ranges of maps
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// `true` with the probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+}
+
+/*
+ * Terrain
+ */
+
+/// # Kind of generated map
+///
+/// `density` is roughly the share of earth cells, from `0` (only sea) to `1` (only earth)
+///
+/// - `Uniform`: every cell is earth with the probability `density`, giving many small islands
+/// - `ValueNoise`: smoothed random heights, the lowest ones being earth. `scale` is the size in
+///   cells of the largest features, each of the `octaves` adds details twice as small
+/// - `Caves`: uniform noise smoothed by `steps` rounds of a cellular automaton, a cell becoming
+///   earth when at least 5 of its 8 neighbours are earth (4 if it already was). Cells outside of
+///   the map count as earth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terrain {
+    Uniform {
+        density: f64,
+    },
+    ValueNoise {
+        density: f64,
+        scale: usize,
+        octaves: usize,
+    },
+    Caves {
+        density: f64,
+        steps: usize,
+    },
+}
+
+/// Names accepted by [`terrain`]
+pub const TERRAIN_NAMES: [&str; 3] = ["uniform", "noise", "caves"];
+
+/// Get a [`Terrain`] with its default parameters from its name (see [`TERRAIN_NAMES`])
+pub fn terrain(name: &str) -> Option<Terrain> {
+    match name {
+        "uniform" => Some(Terrain::Uniform { density: 0.5 }),
+        "noise" => Some(Terrain::ValueNoise {
+            density: 0.5,
+            scale: 16,
+            octaves: 3,
+        }),
+        "caves" => Some(Terrain::Caves {
+            density: 0.45,
+            steps: 4,
+        }),
+        _ => None,
+    }
+}
+
+/*
+ * Generator
+ */
+
+/// # Deterministic random map generator
+///
+/// ```
+/// # use garm_playground::libs::island::{terrain, Generator};
+/// let generator = Generator::new(40, 10).seed(42);
+/// let map = generator.generate(&terrain("caves").unwrap());
+///
+/// assert_eq!(map.height(), 10);
+/// assert_eq!(map.to_string(), generator.generate(&terrain("caves").unwrap()).to_string());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generator {
+    width: usize,
+    height: usize,
+    seed: u64,
+}
+
+impl Generator {
+    /// Generator of `width` by `height` maps, with the seed `0`
+    pub fn new(width: usize, height: usize) -> Self {
+        Generator {
+            width,
+            height,
+            seed: 0,
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generate a map of unmarked earth and sea, in the default [`MapFormat`](super::MapFormat)
+    pub fn generate(&self, terrain: &Terrain) -> Map {
+        let earth = match *terrain {
+            Terrain::Uniform { density } => self.uniform(density),
+            Terrain::ValueNoise {
+                density,
+                scale,
+                octaves,
+            } => self.value_noise(density, scale, octaves),
+            Terrain::Caves { density, steps } => self.caves(density, steps),
+        };
+
+        let mut grid = Grid::filled(self.width, self.height, Cell::Sea);
+        for (cell, &earth) in grid.cells_mut().iter_mut().zip(earth.cells()) {
+            if earth {
+                *cell = Cell::Earth;
+            }
+        }
+
+        Map::from(grid)
+    }
+
+    fn uniform(&self, density: f64) -> Grid<bool> {
+        let mut random = Random::new(self.seed);
+        let mut earth = Grid::filled(self.width, self.height, false);
+
+        for cell in earth.cells_mut() {
+            *cell = random.chance(density);
+        }
+
+        earth
+    }
+
+    fn value_noise(&self, density: f64, scale: usize, octaves: usize) -> Grid<bool> {
+        let mut earth = Grid::filled(self.width, self.height, false);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                earth[(x, y)] = self.height_at(x, y, scale, octaves) < density;
+            }
+        }
+
+        earth
+    }
+
+    /// Sum of the `octaves` of value noise at `(x, y)`, in `[0, 1)`
+    fn height_at(&self, x: usize, y: usize, scale: usize, octaves: usize) -> f64 {
+        let mut scale = scale.max(1) as f64;
+        let mut amplitude = 1.0;
+        let (mut total, mut weight) = (0.0, 0.0);
+
+        for octave in 0..octaves.max(1) as u64 {
+            let (x, y) = (x as f64 / scale, y as f64 / scale);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+            let corner = |dx: f64, dy: f64| self.lattice(octave, x0 + dx, y0 + dy);
+
+            let top = lerp(corner(0.0, 0.0), corner(1.0, 0.0), tx);
+            let bottom = lerp(corner(0.0, 1.0), corner(1.0, 1.0), tx);
+            total += amplitude * lerp(top, bottom, ty);
+            weight += amplitude;
+
+            scale = (scale / 2.0).max(1.0);
+            amplitude /= 2.0;
+        }
+
+        total / weight
+    }
+
+    /// Random value of a lattice point, the same for every cell around it
+    fn lattice(&self, octave: u64, x: f64, y: f64) -> f64 {
+        let seed = self.seed
+            ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ octave.wrapping_mul(0x1656_67B1_9E37_79F9);
+
+        Random::new(seed).next_f64()
+    }
+
+    fn caves(&self, density: f64, steps: usize) -> Grid<bool> {
+        let mut earth = self.uniform(density);
+        // each step reads the previous generation and writes the other buffer
+        let mut next = earth.clone();
+
+        for _ in 0..steps {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let neighbours = earth_neighbours(&earth, x, y);
+                    next[(x, y)] = neighbours >= 5 || (earth[(x, y)] && neighbours >= 4);
+                }
+            }
+            std::mem::swap(&mut earth, &mut next);
+        }
+
+        earth
+    }
+}
+
+/// Number of earth cells among the 8 neighbours of `(x, y)`, outside cells being earth
+fn earth_neighbours(earth: &Grid<bool>, x: usize, y: usize) -> usize {
+    let mut count = 0;
+
+    for dy in -1..=1_isize {
+        for dx in -1..=1_isize {
+            if (dx, dy) == (0, 0) {
+                continue;
+            }
+
+            let cell = x
+                .checked_add_signed(dx)
+                .zip(y.checked_add_signed(dy))
+                .and_then(|coord| earth.get(coord));
+            if cell.unwrap_or(true) {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::connectivity::Connectivity;
+    use crate::libs::island::labeler::{FloodFill, Labeler};
+    use crate::libs::island::stats::Stats;
+
+    fn earth_share(map: &Map) -> f64 {
+        let earth = map.cursor().iter().filter(|c| c.read() == Cell::Earth);
+        earth.count() as f64 / (map.height() * map.width()) as f64
+    }
+
+    fn island_count(map: Map) -> usize {
+        let Stats(islands) = Stats::new(
+            &FloodFill.label(map, &Connectivity::four()),
+            &Connectivity::four(),
+        );
+        islands.len()
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let sequence = |seed| {
+            let mut random = Random::new(seed);
+            (0..8).map(|_| random.next_u64()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(sequence(7), sequence(7));
+        assert_ne!(sequence(7), sequence(8));
+    }
+
+    #[test]
+    fn random_ranges() {
+        let mut random = Random::new(0);
+
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&random.next_f64()));
+            assert!(random.below(3) < 3);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        for name in TERRAIN_NAMES {
+            let terrain = terrain(name).unwrap();
+            let map = |seed| Generator::new(30, 20).seed(seed).generate(&terrain);
+
+            assert_eq!(map(1).to_string(), map(1).to_string(), "{}", name);
+            assert_ne!(map(1).to_string(), map(2).to_string(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn dimensions() {
+        for name in TERRAIN_NAMES {
+            let map = Generator::new(17, 5).generate(&terrain(name).unwrap());
+
+            assert_eq!(map.height(), 5);
+            assert_eq!(map.line_width(4), Some(17));
+        }
+    }
+
+    #[test]
+    fn density_bounds() {
+        let generator = Generator::new(20, 20);
+
+        for terrain in [
+            Terrain::Uniform { density: 0.0 },
+            Terrain::ValueNoise {
+                density: 0.0,
+                scale: 4,
+                octaves: 2,
+            },
+        ] {
+            assert_eq!(earth_share(&generator.generate(&terrain)), 0.0);
+        }
+        for terrain in [
+            Terrain::Uniform { density: 1.0 },
+            Terrain::ValueNoise {
+                density: 1.0,
+                scale: 4,
+                octaves: 2,
+            },
+            Terrain::Caves {
+                density: 1.0,
+                steps: 3,
+            },
+        ] {
+            assert_eq!(earth_share(&generator.generate(&terrain)), 1.0);
+        }
+    }
+
+    #[test]
+    fn uniform_density() {
+        let map = Generator::new(100, 100).generate(&Terrain::Uniform { density: 0.3 });
+        assert!((earth_share(&map) - 0.3).abs() < 0.02);
+    }
+
+    #[test]
+    fn smoothed_terrains_have_fewer_islands() {
+        let generator = Generator::new(100, 100).seed(3);
+        let uniform = island_count(generator.generate(&terrain("uniform").unwrap()));
+
+        assert!(island_count(generator.generate(&terrain("noise").unwrap())) * 10 < uniform);
+        assert!(island_count(generator.generate(&terrain("caves").unwrap())) * 10 < uniform);
+    }
+
+    #[test]
+    fn earth_neighbours_outside() {
+        let earth = Grid::filled(3, 3, false);
+
+        assert_eq!(earth_neighbours(&earth, 1, 1), 0);
+        assert_eq!(earth_neighbours(&earth, 0, 0), 5);
+        assert_eq!(earth_neighbours(&earth, 1, 0), 3);
+    }
+}
@@ -0,0 +1,194 @@
+use super::cursor::Cursor;
+use std::ops::{Index, IndexMut};
+
+/// # A rectangular grid of cells of any type
+///
+/// Cells are stored line after line in a single buffer, and can be indexed by their `(x, y)`
+/// coordinate, `y` growing downward. [`Map`](super::Map) is built on a grid of
+/// [`CellKind`](super::CellKind), any other raster (greyscale pixels, land-use codes...) can be
+/// labelled the same way with a [`Filler`](super::Filler).
+///
+/// ```
+/// # use garm_playground::libs::island::Grid;
+/// let mut grid = Grid::filled(3, 2, 0u8);
+/// grid[(2, 1)] = 255;
+///
+/// assert_eq!(grid.get((2, 1)), Some(255));
+/// assert_eq!(grid.get((3, 1)), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Grid<T> {
+    /// Grid of `width` by `height` cells given line after line, `None` when the number of cells
+    /// does not match
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Option<Self> {
+        (width.checked_mul(height)? == cells.len()).then_some(Grid {
+            cells,
+            width,
+            height,
+        })
+    }
+
+    /// Grid of `width` by `height` copies of `value`
+    pub fn filled(width: usize, height: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid {
+            cells: vec![value; width * height],
+            width,
+            height,
+        }
+    }
+
+    /// Grid from its lines of cells, short lines being padded with `padding`
+    pub fn from_lines(lines: Vec<Vec<T>>, padding: T) -> Self
+    where
+        T: Clone,
+    {
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let height = lines.len();
+        let mut cells = Vec::with_capacity(width * height);
+
+        for line in lines {
+            let missing = width - line.len();
+            cells.extend(line);
+            cells.extend(std::iter::repeat_n(padding.clone(), missing));
+        }
+
+        Grid {
+            cells,
+            width,
+            height,
+        }
+    }
+
+    /// Number of lines
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of cells of every line
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells of the line `y`, `None` when the line does not exist
+    pub fn line_width(&self, y: usize) -> Option<usize> {
+        (y < self.height).then_some(self.width)
+    }
+
+    /// `true` when `(x, y)` is inside of the grid
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, coord: (usize, usize)) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.offset(coord).map(|i| self.cells[i])
+    }
+
+    pub fn write(&mut self, coord: (usize, usize), new_cell: T) -> Option<()> {
+        let i = self.offset(coord)?;
+        self.cells[i] = new_cell;
+        Some(())
+    }
+
+    /// Every cell, line after line
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    /// Every cell, line after line
+    pub fn cells_mut(&mut self) -> &mut [T] {
+        &mut self.cells
+    }
+
+    pub fn cursor(&self) -> Cursor<'_, Grid<T>> {
+        Cursor::new(self)
+    }
+
+    /// Position of `(x, y)` in the buffer
+    fn offset(&self, coord: (usize, usize)) -> Option<usize> {
+        let (x, y) = coord;
+        self.contains(coord).then_some(y * self.width + x)
+    }
+}
+
+/// # The cell at `(x, y)`
+///
+/// Panics when the coordinate is outside of the grid, see [`Grid::get`] for a checked access
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match self.offset((x, y)) {
+            Some(i) => &self.cells[i],
+            None => panic!("[FATAL] invalid grid index x:{} y:{}", x, y),
+        }
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        match self.offset((x, y)) {
+            Some(i) => &mut self.cells[i],
+            None => panic!("[FATAL] invalid grid index x:{} y:{}", x, y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        assert_eq!(Grid::new(2, 3, vec![0; 6]).map(|g| g.height()), Some(3));
+        assert_eq!(Grid::new(2, 3, vec![0; 5]), None);
+        assert_eq!(Grid::new(usize::MAX, 2, vec![0; 0]), None);
+    }
+
+    #[test]
+    fn from_lines() {
+        let grid = Grid::from_lines(vec![vec!['a'], vec!['b', 'c', 'd'], vec![]], '.');
+
+        assert_eq!((grid.width(), grid.height()), (3, 3));
+        assert_eq!(grid.cells(), ['a', '.', '.', 'b', 'c', 'd', '.', '.', '.']);
+    }
+
+    #[test]
+    fn get_and_write() {
+        let mut grid = Grid::filled(2, 2, 1u16);
+
+        assert_eq!(grid.write((1, 0), 7), Some(()));
+        assert_eq!(grid.write((2, 0), 7), None);
+        assert_eq!(grid.get((1, 0)), Some(7));
+        assert_eq!(grid.get((0, 2)), None);
+        assert_eq!(grid.cells(), [1, 7, 1, 1]);
+    }
+
+    #[test]
+    fn index() {
+        let mut grid = Grid::filled(2, 1, String::new());
+        grid[(1, 0)].push_str("non-copy cells");
+
+        assert_eq!(grid[(1, 0)], "non-copy cells");
+        assert!(grid.contains((1, 0)));
+        assert!(!grid.contains((0, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid grid index x:0 y:1")]
+    fn index_outside() {
+        let grid = Grid::filled(2, 1, 0);
+        let _ = grid[(0, 1)];
+    }
+}
@@ -0,0 +1,327 @@
+use super::cell::Cell;
+use super::connectivity::Connectivity;
+use super::disjoint_set::DisjointSet;
+use super::grid::Grid;
+use super::map::Map;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Map of the land of `heights` above the water `level`, ready to be labelled
+pub fn land(heights: &Grid<u8>, level: u8) -> Map {
+    let cells = heights
+        .cells()
+        .iter()
+        .map(|&height| match height > level {
+            true => Cell::Earth,
+            false => Cell::Sea,
+        })
+        .collect();
+    let grid = Grid::new(heights.width(), heights.height(), cells).expect("[FATAL] same size");
+
+    Map::from(grid)
+}
+
+/// # Islands at a single water level
+///
+/// - `level`: the water level, land being every cell above it
+/// - `islands`: number of islands
+/// - `splits`: islands of the level below which split into several islands at this level
+/// - `sunk`: islands of the level below which are under water at this level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterLevel {
+    pub level: u8,
+    pub islands: usize,
+    pub splits: usize,
+    pub sunk: usize,
+}
+
+/// # Node of the merge tree of a heightmap
+///
+/// An island existing from the water level `level` down to the level where it merges into its
+/// parent. Peaks have no children, the other nodes are the merge of their `children`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeNode {
+    pub level: u8,
+    pub children: Vec<usize>,
+}
+
+/// # Island count at every water level of a heightmap
+///
+/// The cells are added from the highest to the lowest into a [`DisjointSet`], as if the water
+/// was falling: each level only unions the cells of its height, islands appearing at their peak
+/// and merging on their saddles. The merges are kept in a merge tree, read upward they are the
+/// splits of rising water.
+///
+/// Levels go from the one where every cell is land (or `0`) up to the highest cell, where no
+/// island is left.
+///
+/// ```
+/// # use garm_playground::libs::island::{Connectivity, ParseOptions, Sweep};
+/// let (heights, _) = ParseOptions::new().parse_heights("3130\n").unwrap();
+/// let sweep = Sweep::new(&heights, &Connectivity::four());
+/// let islands: Vec<usize> = sweep.levels().iter().map(|level| level.islands).collect();
+///
+/// assert_eq!(islands, [1, 2, 2, 0]);
+/// assert_eq!(sweep.levels()[1].splits, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    levels: Vec<WaterLevel>,
+    tree: Vec<MergeNode>,
+}
+
+impl Sweep {
+    pub fn new(heights: &Grid<u8>, connectivity: &Connectivity) -> Self {
+        let max = heights.cells().iter().copied().max().unwrap_or(0);
+        let lowest = heights
+            .cells()
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(0)
+            .saturating_sub(1);
+
+        let mut by_height: Vec<Vec<(usize, usize)>> = vec![vec![]; max as usize + 1];
+        for cursor in heights.cursor().iter() {
+            by_height[cursor.read() as usize].push(cursor.coord());
+        }
+
+        let mut sets = DisjointSet::new();
+        let mut set_of: Grid<Option<usize>> = Grid::filled(heights.width(), heights.height(), None);
+        // merge tree node of every island, by representative
+        let mut node_of: HashMap<usize, usize> = HashMap::new();
+        let mut tree: Vec<MergeNode> = vec![];
+        let mut levels = vec![WaterLevel {
+            level: max,
+            islands: 0,
+            splits: 0,
+            sunk: 0,
+        }];
+
+        // cells of `height` are the land of the level below
+        for height in (lowest + 1..=max).rev() {
+            let level = height - 1;
+            // islands of the level above held by each set
+            let mut held: HashMap<usize, Vec<usize>> = node_of
+                .drain()
+                .map(|(root, node)| (root, vec![node]))
+                .collect();
+
+            for &coord in &by_height[height as usize] {
+                let set = sets.make_set();
+                set_of[coord] = Some(set);
+                held.insert(set, vec![]);
+
+                let cursor = heights
+                    .cursor()
+                    .get(coord)
+                    .expect("[FATAL] cell of the grid");
+                for &dir in connectivity.directions() {
+                    let neighbour = cursor.move_dir(dir).and_then(|c| set_of[c.coord()]);
+
+                    if let Some(neighbour) = neighbour {
+                        let (a, b) = (sets.find(set), sets.find(neighbour));
+                        if a != b {
+                            let mut nodes = held.remove(&a).unwrap_or_default();
+                            nodes.extend(held.remove(&b).unwrap_or_default());
+                            held.insert(sets.union(a, b), nodes);
+                        }
+                    }
+                }
+            }
+
+            let (mut splits, mut sunk) = (0, 0);
+            for (root, nodes) in held {
+                let node = match nodes.len() {
+                    1 => nodes[0],
+                    count => {
+                        match count {
+                            0 => sunk += 1,
+                            _ => splits += 1,
+                        }
+                        tree.push(MergeNode {
+                            level,
+                            children: nodes,
+                        });
+                        tree.len() - 1
+                    }
+                };
+                node_of.insert(root, node);
+            }
+
+            let above = levels.last_mut().expect("[FATAL] highest level");
+            (above.splits, above.sunk) = (splits, sunk);
+            levels.push(WaterLevel {
+                level,
+                islands: sets.set_count(),
+                splits: 0,
+                sunk: 0,
+            });
+        }
+
+        levels.reverse();
+        Sweep { levels, tree }
+    }
+
+    /// Every water level, from the lowest to the highest
+    pub fn levels(&self) -> &[WaterLevel] {
+        &self.levels
+    }
+
+    pub fn level(&self, level: u8) -> Option<&WaterLevel> {
+        self.levels.iter().find(|water| water.level == level)
+    }
+
+    /// Nodes of the merge tree, children before their parent
+    pub fn tree(&self) -> &[MergeNode] {
+        &self.tree
+    }
+
+    /// Water levels where islands split (or merge, the water falling)
+    pub fn split_levels(&self) -> Vec<u8> {
+        let levels = self.levels.iter().filter(|water| water.splits > 0);
+        levels.map(|water| water.level).collect()
+    }
+}
+
+/// Table of the water levels, from the lowest to the highest
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>8} {:>8} {:>8} {:>8}",
+            "level", "islands", "splits", "sunk"
+        )?;
+
+        for water in &self.levels {
+            writeln!(
+                f,
+                "{:>8} {:>8} {:>8} {:>8}",
+                water.level, water.islands, water.splits, water.sunk
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::labeler::{FloodFill, Labeler};
+    use crate::libs::island::map::ParseOptions;
+    use crate::libs::island::stats::Stats;
+
+    const TWO_PEAKS: &str = "\
+00000000
+01233210
+01243510
+01233210
+00000000
+";
+
+    fn heights(raw: &str) -> Grid<u8> {
+        let (heights, _) = ParseOptions::new().parse_heights(raw).unwrap();
+        heights
+    }
+
+    fn island_counts(sweep: &Sweep) -> Vec<(u8, usize)> {
+        let levels = sweep.levels().iter();
+        levels.map(|water| (water.level, water.islands)).collect()
+    }
+
+    /// Island count of a level computed by a full labeling
+    fn labelled_count(heights: &Grid<u8>, level: u8, connectivity: &Connectivity) -> usize {
+        let map = FloodFill.label(land(heights, level), connectivity);
+        let Stats(islands) = Stats::new(&map, connectivity);
+        islands.len()
+    }
+
+    #[test]
+    fn two_peaks() {
+        let sweep = Sweep::new(&heights(TWO_PEAKS), &Connectivity::four());
+
+        assert_eq!(
+            island_counts(&sweep),
+            [(0, 1), (1, 1), (2, 1), (3, 2), (4, 1), (5, 0)]
+        );
+        assert_eq!(sweep.split_levels(), [3]);
+        assert_eq!(sweep.level(4).map(|water| water.sunk), Some(1));
+        assert_eq!(sweep.level(5).map(|water| water.sunk), Some(1));
+        assert_eq!(sweep.level(9), None);
+    }
+
+    #[test]
+    fn merge_tree() {
+        let sweep = Sweep::new(&heights(TWO_PEAKS), &Connectivity::four());
+        let tree = sweep.tree();
+
+        // the two peaks, then their merge on the saddle
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[2].level, 2);
+        let mut peaks: Vec<u8> = tree[2].children.iter().map(|&i| tree[i].level).collect();
+        peaks.sort();
+        assert_eq!(peaks, [3, 4]);
+        assert!(tree[0].children.is_empty() && tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn lowest_level_is_all_land() {
+        let sweep = Sweep::new(&heights("23\n42\n"), &Connectivity::four());
+
+        assert_eq!(island_counts(&sweep), [(1, 1), (2, 2), (3, 1), (4, 0)]);
+        assert_eq!(sweep.levels()[1].splits, 1);
+    }
+
+    #[test]
+    fn flat_map() {
+        let sweep = Sweep::new(&heights("000\n000\n"), &Connectivity::four());
+
+        assert_eq!(island_counts(&sweep), [(0, 0)]);
+        assert!(sweep.tree().is_empty());
+    }
+
+    #[test]
+    fn several_splits_on_a_level() {
+        let sweep = Sweep::new(&heights("51515\n11111\n51515\n"), &Connectivity::four());
+        let water = sweep.level(1).unwrap();
+
+        assert_eq!((water.islands, water.splits, water.sunk), (6, 1, 0));
+        assert_eq!(sweep.tree().last().map(|node| node.children.len()), Some(6));
+    }
+
+    #[test]
+    fn matches_full_labeling() {
+        let mut random = crate::libs::island::generator::Random::new(7);
+        let cells = (0..40 * 30).map(|_| random.below(10) as u8).collect();
+        let heights = Grid::new(40, 30, cells).unwrap();
+
+        for connectivity in [Connectivity::four(), Connectivity::eight()] {
+            let sweep = Sweep::new(&heights, &connectivity);
+
+            for water in sweep.levels() {
+                let expected = labelled_count(&heights, water.level, &connectivity);
+                assert_eq!(water.islands, expected, "level {}", water.level);
+            }
+        }
+    }
+
+    #[test]
+    fn land_map() {
+        let map = land(&heights("0123\n"), 1);
+        assert_eq!(map.to_string(), "  ##\n");
+    }
+
+    #[test]
+    fn display() {
+        let sweep = Sweep::new(&heights("121\n"), &Connectivity::four());
+        let table = [
+            "   level  islands   splits     sunk",
+            "       0        1        0        0",
+            "       1        1        0        0",
+            "       2        0        0        1",
+        ];
+
+        assert_eq!(sweep.to_string(), table.join("\n") + "\n");
+    }
+}
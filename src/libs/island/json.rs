@@ -0,0 +1,340 @@
+use super::cell::Cell;
+use super::error::{Error, ErrorList};
+use super::label::{Label, LabelEncoder};
+use super::map::Map;
+use super::stats::Stats;
+use std::collections::HashMap;
+use std::fmt;
+
+/// # A JSON value
+///
+/// Displayed as compact JSON, object keys keep their insertion order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(string) => write_string(f, string),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{}{}", separator, value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{}", separator)?;
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            _ if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            _ => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/*
+ * Report
+ */
+
+/// # JSON report of a labelled map
+///
+/// ```text
+/// {
+///   "width": 3,                  // number of cells per line, null on error
+///   "height": 1,                 // number of lines, null on error
+///   "count": 2,                  // number of islands, null on error
+///   "islands": [                 // ordered by label
+///     {
+///       "label": 0,              // island number
+///       "mark": "0",             // label rendered by the encoder, null when it cannot be
+///       "area": 1,               // number of cells
+///       "bounding_box": { "x": 0, "y": 0, "width": 1, "height": 1 },
+///       "cells": [[0, 0]]        // [x, y] of every cell, in reading order
+///     },
+///     ...
+///   ],
+///   "errors": [],                // see the conversion of an ErrorList
+///   "warnings": []               // errors repaired by a lenient parsing, same objects as errors
+/// }
+/// ```
+///
+/// Coordinates start at `0` from the top left corner.
+pub fn report(map: &Map, stats: &Stats, encoder: &dyn LabelEncoder, warnings: &ErrorList) -> Json {
+    let Stats(stats) = stats;
+    let mut cells: Vec<Vec<Json>> = vec![vec![]; stats.len()];
+    // labels may have gaps, e.g. with preserved marks
+    let islands: HashMap<Label, usize> = stats
+        .iter()
+        .enumerate()
+        .map(|(i, island)| (island.label, i))
+        .collect();
+
+    for cursor in map.cursor().iter() {
+        if let Cell::MarkedEarth(label) = cursor.read() {
+            let (x, y) = cursor.coord();
+            let island = islands[&label];
+            cells[island].push(Json::Array(vec![Json::Number(x), Json::Number(y)]));
+        }
+    }
+
+    let islands = stats
+        .iter()
+        .zip(cells)
+        .map(|(island, cells)| {
+            let bounding_box = island.bounding_box;
+            let (x, y) = bounding_box.min;
+
+            Json::Object(vec![
+                ("label", Json::Number(island.label)),
+                (
+                    "mark",
+                    encoder
+                        .encode(island.label)
+                        .map_or(Json::Null, Json::String),
+                ),
+                ("area", Json::Number(island.area)),
+                (
+                    "bounding_box",
+                    Json::Object(vec![
+                        ("x", Json::Number(x)),
+                        ("y", Json::Number(y)),
+                        ("width", Json::Number(bounding_box.width())),
+                        ("height", Json::Number(bounding_box.height())),
+                    ]),
+                ),
+                ("cells", Json::Array(cells)),
+            ])
+        })
+        .collect();
+
+    Json::Object(vec![
+        ("width", Json::Number(map.width())),
+        ("height", Json::Number(map.height())),
+        ("count", Json::Number(stats.len())),
+        ("islands", Json::Array(islands)),
+        ("errors", Json::Array(vec![])),
+        ("warnings", error_array(warnings)),
+    ])
+}
+
+/// # JSON report of a map that could not be parsed
+///
+/// Same schema as [`report`], with `null` dimensions, no island, no warning and one object per
+/// error:
+///
+/// ```text
+/// {
+///   "kind": "invalid_char",      // snake_case name of the Error variant
+///   "message": "invalid character '[' found at position 1:3",
+///   "line": 1,                   // 1-based, only when the error has a position
+///   "col": 3,                    // 1-based, only when the error has a column
+///   ...                          // the other fields of the variant: "char", "expected",
+///                                // "found", "label", "other"
+/// }
+/// ```
+impl From<&ErrorList> for Json {
+    fn from(errors: &ErrorList) -> Self {
+        Json::Object(vec![
+            ("width", Json::Null),
+            ("height", Json::Null),
+            ("count", Json::Null),
+            ("islands", Json::Array(vec![])),
+            ("errors", error_array(errors)),
+            ("warnings", Json::Array(vec![])),
+        ])
+    }
+}
+
+/// Array of one object per error
+fn error_array(errors: &ErrorList) -> Json {
+    let ErrorList(errors) = errors;
+    Json::Array(errors.iter().map(Json::from).collect())
+}
+
+impl From<&Error> for Json {
+    fn from(error: &Error) -> Self {
+        let (kind, fields) = match *error {
+            Error::EmptyMap => ("empty_map", vec![]),
+            Error::EmptyLine(line) => ("empty_line", vec![("line", Json::Number(line))]),
+            Error::InvalidChar { char, line, col } => (
+                "invalid_char",
+                vec![
+                    ("line", Json::Number(line)),
+                    ("col", Json::Number(col)),
+                    ("char", Json::String(char.to_string())),
+                ],
+            ),
+            Error::RaggedLine {
+                line,
+                expected,
+                found,
+            } => (
+                "ragged_line",
+                vec![
+                    ("line", Json::Number(line)),
+                    ("expected", Json::Number(expected)),
+                    ("found", Json::Number(found)),
+                ],
+            ),
+            Error::MixedLabels {
+                label,
+                other,
+                line,
+                col,
+            } => (
+                "mixed_labels",
+                vec![
+                    ("line", Json::Number(line)),
+                    ("col", Json::Number(col)),
+                    ("label", Json::Number(label)),
+                    ("other", Json::Number(other)),
+                ],
+            ),
+            Error::DuplicateLabel { label, line, col } => (
+                "duplicate_label",
+                vec![
+                    ("line", Json::Number(line)),
+                    ("col", Json::Number(col)),
+                    ("label", Json::Number(label)),
+                ],
+            ),
+            Error::UnlabelledEarth { line, col } => (
+                "unlabelled_earth",
+                vec![("line", Json::Number(line)), ("col", Json::Number(col))],
+            ),
+        };
+
+        let mut members = vec![
+            ("kind", Json::String(kind.to_string())),
+            ("message", Json::String(error.to_string())),
+        ];
+        members.extend(fields);
+
+        Json::Object(members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::connectivity::Connectivity;
+    use crate::libs::island::label::Alphabet;
+    use crate::libs::island::labeler::{FloodFill, Labeler};
+    use crate::libs::island::preserve::PreserveMarks;
+
+    #[test]
+    fn display() {
+        let json = Json::Object(vec![
+            ("a", Json::Array(vec![Json::Number(1), Json::Null])),
+            ("b", Json::String("\"quoted\"\\\n\u{1}é".to_string())),
+            ("c", Json::Object(vec![])),
+        ]);
+
+        assert_eq!(
+            json.to_string(),
+            r#"{"a":[1,null],"b":"\"quoted\"\\\n\u0001é","c":{}}"#
+        );
+    }
+
+    #[test]
+    fn labelled_map() {
+        let map = FloodFill.label("## \n  #\n".parse().unwrap(), &Connectivity::four());
+        let stats = Stats::new(&map, &Connectivity::four());
+
+        assert_eq!(
+            report(&map, &stats, &Alphabet::GREEK, &ErrorList(vec![])).to_string(),
+            concat!(
+                r#"{"width":3,"height":2,"count":2,"islands":["#,
+                r#"{"label":0,"mark":"α","area":2,"#,
+                r#""bounding_box":{"x":0,"y":0,"width":2,"height":1},"cells":[[0,0],[1,0]]},"#,
+                r#"{"label":1,"mark":"β","area":1,"#,
+                r#""bounding_box":{"x":2,"y":1,"width":1,"height":1},"cells":[[2,1]]}"#,
+                r#"],"errors":[],"warnings":[]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn labels_with_gaps() {
+        let map = "5 # 2\n".parse().unwrap();
+        let map = PreserveMarks(&FloodFill).label(map, &Connectivity::four());
+        let stats = Stats::new(&map, &Connectivity::four());
+        let Json::Object(members) = report(&map, &stats, &Alphabet::BASE36, &ErrorList(vec![]))
+        else {
+            panic!("report is an object");
+        };
+        let islands: Vec<String> = match &members[3] {
+            ("islands", Json::Array(islands)) => islands.iter().map(Json::to_string).collect(),
+            member => panic!("unexpected member {:?}", member),
+        };
+
+        assert_eq!(islands.len(), 3);
+        // the new island is numbered after the highest kept label
+        for (island, (label, x)) in islands.iter().zip([(2, 4), (5, 0), (6, 2)]) {
+            assert!(
+                island.starts_with(&format!(r#"{{"label":{},"#, label)),
+                "{}",
+                island
+            );
+            assert!(
+                island.ends_with(&format!(r#""cells":[[{},0]]}}"#, x)),
+                "{}",
+                island
+            );
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let errors = ErrorList(vec![
+            Error::EmptyLine(2),
+            Error::InvalidChar {
+                char: '[',
+                line: 3,
+                col: 1,
+            },
+        ]);
+
+        assert_eq!(
+            Json::from(&errors).to_string(),
+            concat!(
+                r#"{"width":null,"height":null,"count":null,"islands":[],"errors":["#,
+                r#"{"kind":"empty_line","message":"empty line found at line '2'","line":2},"#,
+                r#"{"kind":"invalid_char","#,
+                r#""message":"invalid character '[' found at position 3:1","#,
+                r#""line":3,"col":1,"char":"["}"#,
+                r#"],"warnings":[]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use super::cell::Cell;
+use super::format::MapFormat;
+
+/// Numeric identifier of an island, islands are numbered from `0`
+pub type Label = usize;
+
+/// # Render island labels as text
+///
+/// An encoder turns the numeric [`Label`] of an island into the text written in the output map.
+pub trait LabelEncoder {
+    /// Text for `label`, `None` when the encoder cannot represent it
+    fn encode(&self, label: Label) -> Option<String>;
+
+    /// Text written between two cells of a same line
+    fn separator(&self) -> &str {
+        ""
+    }
+
+    /// `true` when a label can be rendered as the sea or earth character of `format`, the
+    /// rendered map would then read back as another map
+    fn collides(&self, _format: &MapFormat) -> bool {
+        false
+    }
+
+    /// Text written for a whole cell, sea and earth being taken from `format`
+    ///
+    /// labels that cannot be encoded are rendered as unmarked earth
+    fn encode_cell(&self, cell: Cell, format: &MapFormat) -> String {
+        match cell {
+            Cell::MarkedEarth(label) => self
+                .encode(label)
+                .unwrap_or_else(|| format.earth().to_string()),
+            _ => format.char(cell).to_string(),
+        }
+    }
+}
+
+/*
+ * Alphabet
+ */
+
+/// # Single character encoder
+///
+/// Each label is written with exactly one character, so the output map keeps the input geometry.
+/// Characters are taken in order from a list of inclusive char ranges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alphabet(&'static [(char, char)]);
+
+impl Alphabet {
+    /// No character at all, e.g. for maps without marks
+    pub const NONE: Alphabet = Alphabet(&[]);
+
+    /// `'0'..='9'` then `'a'..='z'`: 36 labels
+    pub const BASE36: Alphabet = Alphabet(&[('0', '9'), ('a', 'z')]);
+
+    /// [`Alphabet::BASE36`] then `'A'..='Z'`: 62 labels
+    pub const BASE62: Alphabet = Alphabet(&[('0', '9'), ('a', 'z'), ('A', 'Z')]);
+
+    /// Greek lowercase letters `'α'..='ω'`: 25 labels, used to render lakes
+    pub const GREEK: Alphabet = Alphabet(&[('\u{03B1}', '\u{03C9}')]);
+
+    /// [`Alphabet::BASE62`] followed by latin extended, greek, cyrillic and CJK ideographs
+    ///
+    /// Note: CJK ideographs are rendered double width by most terminals
+    pub const UNICODE: Alphabet = Alphabet(&[
+        ('0', '9'),
+        ('a', 'z'),
+        ('A', 'Z'),
+        ('\u{0100}', '\u{024F}'),
+        ('\u{0391}', '\u{03A1}'),
+        ('\u{03A3}', '\u{03A9}'),
+        ('\u{03B1}', '\u{03C9}'),
+        ('\u{0410}', '\u{044F}'),
+        ('\u{4E00}', '\u{9FFF}'),
+    ]);
+
+    /// Number of labels this alphabet can represent
+    pub fn len(&self) -> usize {
+        let Alphabet(ranges) = self;
+        ranges.iter().map(|&(from, to)| range_len(from, to)).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn char(&self, label: Label) -> Option<char> {
+        let Alphabet(ranges) = self;
+        let mut label = label;
+
+        for &(from, to) in ranges.iter() {
+            let len = range_len(from, to);
+            if label < len {
+                return char::from_u32(from as u32 + label as u32);
+            }
+            label -= len;
+        }
+
+        None
+    }
+
+    pub fn label(&self, c: char) -> Option<Label> {
+        let Alphabet(ranges) = self;
+        let mut offset = 0;
+
+        for &(from, to) in ranges.iter() {
+            if (from..=to).contains(&c) {
+                return Some(offset + (c as u32 - from as u32) as Label);
+            }
+            offset += range_len(from, to);
+        }
+
+        None
+    }
+}
+
+fn range_len(from: char, to: char) -> usize {
+    (to as u32 - from as u32 + 1) as usize
+}
+
+impl LabelEncoder for Alphabet {
+    fn encode(&self, label: Label) -> Option<String> {
+        self.char(label).map(String::from)
+    }
+
+    fn collides(&self, format: &MapFormat) -> bool {
+        [format.sea(), format.earth()]
+            .into_iter()
+            .any(|c| self.label(c).is_some())
+    }
+}
+
+/*
+ * Csv
+ */
+
+/// # Numeric encoder
+///
+/// Each cell becomes a comma separated field carrying the full island number,
+/// sea cells are empty fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Csv;
+
+impl LabelEncoder for Csv {
+    fn encode(&self, label: Label) -> Option<String> {
+        Some(label.to_string())
+    }
+
+    fn separator(&self) -> &str {
+        ","
+    }
+
+    fn encode_cell(&self, cell: Cell, format: &MapFormat) -> String {
+        match cell {
+            Cell::Sea => String::new(),
+            Cell::Earth => format.earth().to_string(),
+            Cell::MarkedEarth(label) => label.to_string(),
+        }
+    }
+}
+
+/// Names accepted by [`alphabet`]
+pub const ALPHABET_NAMES: [&str; 5] = ["base36", "base62", "greek", "unicode", "none"];
+
+/// Get an [`Alphabet`] from its name (see [`ALPHABET_NAMES`])
+pub fn alphabet(name: &str) -> Option<Alphabet> {
+    match name {
+        "base36" => Some(Alphabet::BASE36),
+        "base62" => Some(Alphabet::BASE62),
+        "greek" => Some(Alphabet::GREEK),
+        "unicode" => Some(Alphabet::UNICODE),
+        "none" => Some(Alphabet::NONE),
+        _ => None,
+    }
+}
+
+/// Names accepted by [`encoder`]
+pub const ENCODER_NAMES: [&str; 5] = ["base36", "base62", "greek", "unicode", "csv"];
+
+/// Get an encoder from its name (see [`ENCODER_NAMES`])
+pub fn encoder(name: &str) -> Option<Box<dyn LabelEncoder>> {
+    match name {
+        "csv" => Some(Box::new(Csv)),
+        "none" => None,
+        _ => Some(Box::new(alphabet(name)?)),
+    }
+}
+
+/// Encoder rendering labels with the marks of `format`, [`Csv`] when it has none: unlike a fixed
+/// alphabet, it never collides with the sea and earth characters
+pub fn format_encoder(format: &MapFormat) -> Box<dyn LabelEncoder> {
+    match format.marks().is_empty() {
+        true => Box::new(Csv),
+        false => Box::new(format.marks()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod alphabet {
+        use super::*;
+
+        #[test]
+        fn lengths() {
+            assert_eq!(Alphabet::BASE36.len(), 36);
+            assert_eq!(Alphabet::BASE62.len(), 62);
+            assert_eq!(Alphabet::GREEK.len(), 25);
+            assert!(Alphabet::UNICODE.len() > 20_000);
+        }
+
+        #[test]
+        fn base36_matches_radix_digits() {
+            for label in 0..36 {
+                let expected = char::from_digit(label as u32, 36);
+                assert_eq!(Alphabet::BASE36.char(label), expected);
+            }
+            assert_eq!(Alphabet::BASE36.char(36), None);
+        }
+
+        #[test]
+        fn base62_uppercase() {
+            assert_eq!(Alphabet::BASE62.char(36), Some('A'));
+            assert_eq!(Alphabet::BASE62.char(61), Some('Z'));
+            assert_eq!(Alphabet::BASE62.char(62), None);
+        }
+
+        #[test]
+        fn unicode_round_trip() {
+            let alphabet = Alphabet::UNICODE;
+            for label in 0..alphabet.len() {
+                let c = alphabet.char(label).unwrap();
+                assert_eq!(alphabet.label(c), Some(label));
+            }
+            assert_eq!(alphabet.char(alphabet.len()), None);
+        }
+
+        #[test]
+        fn never_collides_with_map_chars() {
+            use crate::libs::island::cell::{EARTH_CHAR, SEA_CHAR};
+
+            assert_eq!(Alphabet::UNICODE.label(SEA_CHAR), None);
+            assert_eq!(Alphabet::UNICODE.label(EARTH_CHAR), None);
+        }
+    }
+
+    mod encode_cell {
+        use super::*;
+
+        const FORMAT: &MapFormat = &MapFormat::DEFAULT;
+
+        #[test]
+        fn alphabet_overflow_renders_earth() {
+            let cell = Cell::MarkedEarth(36);
+            assert_eq!(Alphabet::BASE36.encode_cell(cell, FORMAT), "#");
+            assert_eq!(Alphabet::BASE62.encode_cell(cell, FORMAT), "A");
+            assert_eq!(Alphabet::BASE36.encode_cell(cell, &MapFormat::DOTS), "X");
+        }
+
+        #[test]
+        fn follows_map_format() {
+            let format = &MapFormat::DOTS;
+            assert_eq!(Alphabet::BASE36.encode_cell(Cell::Sea, format), ".");
+            assert_eq!(Alphabet::BASE36.encode_cell(Cell::Earth, format), "X");
+            assert_eq!(
+                Alphabet::GREEK.encode_cell(Cell::MarkedEarth(0), format),
+                "α"
+            );
+        }
+
+        #[test]
+        fn csv() {
+            assert_eq!(Csv.encode_cell(Cell::Sea, FORMAT), "");
+            assert_eq!(Csv.encode_cell(Cell::Earth, FORMAT), "#");
+            assert_eq!(Csv.encode_cell(Cell::MarkedEarth(1234), FORMAT), "1234");
+        }
+    }
+
+    #[test]
+    fn collisions() {
+        assert!(Alphabet::BASE36.collides(&MapFormat::BINARY));
+        assert!(!Alphabet::BASE36.collides(&MapFormat::DEFAULT));
+        assert!(!Alphabet::GREEK.collides(&MapFormat::BINARY));
+        assert!(!Csv.collides(&MapFormat::BINARY));
+    }
+
+    #[test]
+    fn format_encoders() {
+        let binary = format_encoder(&MapFormat::BINARY);
+        assert_eq!(binary.encode(1).as_deref(), Some("1"));
+        assert_eq!(binary.separator(), ",");
+
+        let dots = format_encoder(&MapFormat::DOTS);
+        assert_eq!(dots.encode(11).as_deref(), Some("b"));
+        assert!(!dots.collides(&MapFormat::DOTS));
+    }
+
+    #[test]
+    fn encoder_by_name() {
+        for name in ENCODER_NAMES {
+            assert!(encoder(name).is_some());
+        }
+        assert!(encoder("base2").is_none());
+        assert!(encoder("none").is_none());
+    }
+
+    #[test]
+    fn alphabet_by_name() {
+        for name in ALPHABET_NAMES {
+            assert!(alphabet(name).is_some());
+        }
+        assert_eq!(alphabet("none").map(|alphabet| alphabet.len()), Some(0));
+        assert!(alphabet("csv").is_none());
+    }
+}
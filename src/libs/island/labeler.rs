@@ -0,0 +1,408 @@
+use super::cell::{Cell, CellKind};
+use super::connectivity::Connectivity;
+use super::disjoint_set::DisjointSet;
+use super::filler::Filler;
+use super::grid::Grid;
+use super::label::Label;
+use super::map::{packed_label, Map};
+use std::thread;
+
+/// # A connected-components engine
+///
+/// Every `Cell::Earth` of the map becomes `Cell::MarkedEarth`, islands being numbered from `0`
+/// in the reading order of their first cell. Already marked cells are left untouched.
+///
+/// All engines produce identical labelings.
+pub trait Labeler {
+    fn label(&self, map: Map, connectivity: &Connectivity) -> Map;
+}
+
+/*
+ * FloodFill
+ */
+
+/// # Flood fill engine
+///
+/// Walks the map and fills each new island with a [`Filler`] (the default engine)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloodFill;
+
+impl Labeler for FloodFill {
+    fn label(&self, mut map: Map, connectivity: &Connectivity) -> Map {
+        let (labels, _) = fill_islands(map.kinds(), connectivity);
+        map.mark(&labels);
+        map
+    }
+}
+
+/// Labels of the islands of unmarked earth of `kinds` in reading order, and their number
+fn fill_islands(
+    kinds: &Grid<CellKind>,
+    connectivity: &Connectivity,
+) -> (Grid<Option<Label>>, usize) {
+    let island =
+        |kind: &CellKind, other: &CellKind| *kind == CellKind::Earth && *other == CellKind::Earth;
+    let mut filler = Filler::new(kinds, connectivity.clone(), island);
+
+    for y in 0..kinds.height() {
+        for x in 0..kinds.width() {
+            filler.fill((x, y));
+        }
+    }
+
+    let island_count = filler.region_count();
+    (filler.into_labels(), island_count)
+}
+
+/*
+ * UnionFind
+ */
+
+/// # Union-find engine
+///
+/// A single raster scan gives each earth cell a provisional label taken from its already
+/// scanned neighbours, equivalent labels being merged in a [`DisjointSet`].
+/// Provisional labels are then resolved to their final label in a second pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnionFind;
+
+impl Labeler for UnionFind {
+    fn label(&self, mut map: Map, connectivity: &Connectivity) -> Map {
+        // connectivity is symmetric: looking backward is enough to see every edge
+        let backward_offsets: Vec<(isize, isize)> = connectivity
+            .offsets()
+            .into_iter()
+            .filter(|&(dx, dy)| dy < 0 || (dy == 0 && dx < 0))
+            .collect();
+
+        let mut sets = DisjointSet::new();
+        let mut provisional: Grid<Option<usize>> = Grid::filled(map.width(), map.height(), None);
+
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                if map[(x, y)] != CellKind::Earth {
+                    continue;
+                }
+
+                let mut label = None;
+                for &(dx, dy) in backward_offsets.iter() {
+                    let neighbour = x
+                        .checked_add_signed(dx)
+                        .zip(y.checked_add_signed(dy))
+                        .and_then(|coord| provisional.get(coord).flatten());
+
+                    if let Some(neighbour) = neighbour {
+                        label = Some(match label {
+                            Some(label) => sets.union(label, neighbour),
+                            None => neighbour,
+                        });
+                    }
+                }
+
+                provisional[(x, y)] = Some(label.unwrap_or_else(|| sets.make_set()));
+            }
+        }
+
+        let mut final_labels: Vec<Option<Label>> = vec![None; sets.len()];
+        let mut next_label = 0;
+
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let root = match provisional[(x, y)] {
+                    Some(label) => sets.find(label),
+                    None => continue,
+                };
+
+                let label = *final_labels[root].get_or_insert_with(|| {
+                    next_label += 1;
+                    next_label - 1
+                });
+                map.write((x, y), Cell::MarkedEarth(label));
+            }
+        }
+
+        map
+    }
+}
+
+/*
+ * Parallel
+ */
+
+/// # Parallel engine
+///
+/// The map is cut into horizontal strips, one per thread, labelled by [`FloodFill`] on scoped
+/// threads. The labels of islands crossing strip boundaries are then merged in a
+/// [`DisjointSet`], and every strip is renumbered on its thread: the labeling is the one of the
+/// sequential engines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parallel {
+    threads: usize,
+}
+
+impl Parallel {
+    /// Engine labelling maps on `threads` threads, at least one
+    pub fn new(threads: usize) -> Self {
+        Parallel {
+            threads: threads.max(1),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+}
+
+/// One thread per available core
+impl Default for Parallel {
+    fn default() -> Self {
+        Parallel::new(thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+}
+
+impl Labeler for Parallel {
+    fn label(&self, mut map: Map, connectivity: &Connectivity) -> Map {
+        let (width, height) = (map.width(), map.height());
+        if width == 0 || height == 0 {
+            return map;
+        }
+        let strip_height = height.div_ceil(self.threads);
+        let strip_len = width * strip_height;
+
+        // labels of every strip labelled on its own, with its number of islands
+        let strips: Vec<(Grid<Option<Label>>, usize)> = thread::scope(|scope| {
+            let workers: Vec<_> = map
+                .kinds()
+                .cells()
+                .chunks(strip_len)
+                .map(|cells| scope.spawn(move || label_strip(cells, width, connectivity)))
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("[FATAL] labeling thread panicked"))
+                .collect()
+        });
+
+        // element of the first label of every strip
+        let mut offsets = vec![];
+        let mut sets = DisjointSet::new();
+        for (_, island_count) in &strips {
+            offsets.push(sets.len());
+            (0..*island_count).for_each(|_| {
+                sets.make_set();
+            });
+        }
+
+        let element = |(x, y): (usize, usize)| {
+            let (strip, _) = &strips[y / strip_height];
+            strip[(x, y % strip_height)].map(|label| offsets[y / strip_height] + label)
+        };
+
+        // connectivity is symmetric: looking upward from the first lines of a strip is enough
+        let upward_offsets: Vec<(isize, isize)> = connectivity
+            .offsets()
+            .into_iter()
+            .filter(|&(_, dy)| dy < 0)
+            .collect();
+        let reach = upward_offsets.iter().map(|(_, dy)| dy.unsigned_abs());
+        let reach = reach.max().unwrap_or(0);
+
+        for top in (strip_height..height).step_by(strip_height) {
+            for y in top..(top + reach).min(height) {
+                for x in 0..width {
+                    let Some(label) = element((x, y)) else {
+                        continue;
+                    };
+
+                    for &(dx, dy) in &upward_offsets {
+                        let neighbour = x
+                            .checked_add_signed(dx)
+                            .zip(y.checked_add_signed(dy))
+                            .filter(|&(nx, ny)| nx < width && ny < top)
+                            .and_then(element);
+
+                        if let Some(neighbour) = neighbour {
+                            sets.union(label, neighbour);
+                        }
+                    }
+                }
+            }
+        }
+
+        // strips and their labels are in reading order, so are the islands first seen
+        let mut final_labels: Vec<Option<Label>> = vec![None; sets.len()];
+        let mut next_label = 0;
+        let labels: Vec<Label> = (0..sets.len())
+            .map(|element| {
+                *final_labels[sets.find(element)].get_or_insert_with(|| {
+                    next_label += 1;
+                    next_label - 1
+                })
+            })
+            .collect();
+
+        thread::scope(|scope| {
+            let (kinds, stored_labels) = map.kinds_and_labels_mut();
+            let chunks = kinds
+                .chunks_mut(strip_len)
+                .zip(stored_labels.chunks_mut(strip_len));
+
+            for ((kinds, stored_labels), ((strip, _), &offset)) in
+                chunks.zip(strips.iter().zip(&offsets))
+            {
+                let labels = &labels;
+
+                scope.spawn(move || {
+                    let cells = kinds.iter_mut().zip(stored_labels);
+                    for ((kind, stored_label), labelled) in cells.zip(strip.cells()) {
+                        if let Some(label) = labelled {
+                            *kind = CellKind::MarkedEarth;
+                            *stored_label = packed_label(labels[offset + label]);
+                        }
+                    }
+                });
+            }
+        });
+
+        map
+    }
+}
+
+/// Labels of the islands of a strip of `cells` filled as by [`FloodFill`], and its number of
+/// islands
+fn label_strip(
+    cells: &[CellKind],
+    width: usize,
+    connectivity: &Connectivity,
+) -> (Grid<Option<Label>>, usize) {
+    let grid = Grid::new(width, cells.len() / width, cells.to_vec()).expect("[FATAL] whole lines");
+    fill_islands(&grid, connectivity)
+}
+
+/// Names accepted by [`labeler`]
+pub const LABELER_NAMES: [&str; 3] = ["flood-fill", "union-find", "parallel"];
+
+/// Get a labeling engine from its name (see [`LABELER_NAMES`])
+///
+/// `parallel` runs on one thread per available core, see [`Parallel::new`] for another count
+pub fn labeler(name: &str) -> Option<Box<dyn Labeler>> {
+    match name {
+        "flood-fill" => Some(Box::new(FloodFill)),
+        "union-find" => Some(Box::new(UnionFind)),
+        "parallel" => Some(Box::<Parallel>::default()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "\
+# ## #
+ ## ##
+#   # 
+## ###
+";
+
+    fn label(labeler: &dyn Labeler, raw_map: &str, connectivity: Connectivity) -> String {
+        labeler
+            .label(raw_map.parse().unwrap(), &connectivity)
+            .to_string()
+    }
+
+    #[test]
+    fn union_find_four() {
+        let expected = "\
+0 11 2
+ 11 22
+3   2 
+33 222
+";
+        assert_eq!(label(&UnionFind, MAP, Connectivity::four()), expected);
+    }
+
+    #[test]
+    fn union_find_eight() {
+        let expected = "\
+0 00 0
+ 00 00
+0   0 
+00 000
+";
+        assert_eq!(label(&UnionFind, MAP, Connectivity::eight()), expected);
+    }
+
+    #[test]
+    fn union_find_keeps_marks() {
+        let map = "#5#\n";
+        assert_eq!(label(&UnionFind, map, Connectivity::four()), "051\n");
+    }
+
+    #[test]
+    fn same_as_flood_fill() {
+        for connectivity in [
+            Connectivity::four(),
+            Connectivity::eight(),
+            Connectivity::knight(),
+        ] {
+            assert_eq!(
+                label(&UnionFind, MAP, connectivity.clone()),
+                label(&FloodFill, MAP, connectivity)
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_same_as_flood_fill() {
+        for connectivity in [
+            Connectivity::four(),
+            Connectivity::eight(),
+            Connectivity::knight(),
+        ] {
+            // more threads than lines too
+            for threads in 1..=6 {
+                assert_eq!(
+                    label(&Parallel::new(threads), MAP, connectivity.clone()),
+                    label(&FloodFill, MAP, connectivity.clone()),
+                    "{} threads",
+                    threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_keeps_marks() {
+        let map = "#5#\n#  \n#5#\n";
+        assert_eq!(
+            label(&Parallel::new(3), map, Connectivity::four()),
+            label(&FloodFill, map, Connectivity::four())
+        );
+        assert_eq!(
+            label(&Parallel::new(3), map, Connectivity::four()),
+            "051\n0  \n052\n"
+        );
+    }
+
+    #[test]
+    fn parallel_threads() {
+        let map = Map::from(vec![vec![]; 3]);
+
+        assert_eq!(Parallel::new(0).threads(), 1);
+        assert!(Parallel::default().threads() >= 1);
+        assert_eq!(
+            Parallel::new(4).label(map.clone(), &Connectivity::four()),
+            map
+        );
+    }
+
+    #[test]
+    fn labeler_by_name() {
+        for name in LABELER_NAMES {
+            assert!(labeler(name).is_some());
+        }
+        assert!(labeler("bfs").is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use super::cell::{Cell, CellKind};
+use super::connectivity::Connectivity;
+use super::cursor::DIRECTIONS;
+use super::filler::Filler;
+use super::grid::Grid;
+use super::label::{Label, LabelEncoder};
+use super::map::Map;
+use std::collections::VecDeque;
+
+/// # A sea region fully enclosed by land
+///
+/// - `label`: lakes are numbered from `0` in reading order
+/// - `island`: label of the island enclosing the lake
+/// - `area`: number of cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lake {
+    pub label: Label,
+    pub island: Option<Label>,
+    pub area: usize,
+}
+
+/// # Sea regions of a labelled map
+///
+/// Sea cells are grouped with the [dual](Connectivity::dual) of the island connectivity.
+/// A sea region touching the border of the map is open ocean, any other sea region is a lake.
+///
+/// Each lake is attributed to the first island met when walking from the border of the map
+/// toward the lake, which is the island surrounding it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lakes {
+    regions: Grid<Option<Label>>,
+    lake_labels: Vec<Option<Label>>,
+    lakes: Vec<Lake>,
+}
+
+enum Region {
+    Island(Label),
+    Sea(usize),
+}
+
+impl Lakes {
+    pub fn new(map: &Map, connectivity: &Connectivity) -> Self {
+        // flood fill every sea region
+        let sea =
+            |kind: &CellKind, other: &CellKind| *kind == CellKind::Sea && *other == CellKind::Sea;
+        let mut filler = Filler::new(map.kinds(), connectivity.dual(), sea);
+
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                filler.fill((x, y));
+            }
+        }
+
+        let sea_count = filler.region_count();
+        let regions = filler.into_labels();
+        let mut sea_areas: Vec<usize> = vec![0; sea_count];
+        let mut sea_on_border: Vec<bool> = vec![false; sea_count];
+
+        for cursor in regions.cursor().iter() {
+            if let Some(region) = *cursor.cell() {
+                sea_areas[region] += 1;
+                sea_on_border[region] |=
+                    DIRECTIONS.iter().any(|&dir| cursor.move_dir(dir).is_none());
+            }
+        }
+
+        // adjacency between islands and sea regions
+        let mut island_seas: Vec<Vec<usize>> = vec![];
+        let mut sea_islands: Vec<Vec<Label>> = vec![vec![]; sea_areas.len()];
+        let mut island_on_border: Vec<bool> = vec![];
+
+        for cursor in map.cursor().iter() {
+            let label = match cursor.read() {
+                Cell::MarkedEarth(label) => label,
+                _ => continue,
+            };
+            if island_seas.len() <= label {
+                island_seas.resize(label + 1, vec![]);
+                island_on_border.resize(label + 1, false);
+            }
+
+            for dir in DIRECTIONS {
+                let coord = match cursor.move_dir(dir) {
+                    Some(next_cursor) => next_cursor.coord(),
+                    None => {
+                        island_on_border[label] = true;
+                        continue;
+                    }
+                };
+
+                if let Some(region) = regions[coord] {
+                    island_seas[label].push(region);
+                    sea_islands[region].push(label);
+                }
+            }
+        }
+
+        for neighbours in island_seas.iter_mut().chain(sea_islands.iter_mut()) {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+
+        // walk from the border of the map toward the inner regions
+        let mut enclosing_islands: Vec<Option<Label>> = vec![None; sea_areas.len()];
+        let mut visited_seas = sea_on_border.clone();
+        let mut visited_islands = island_on_border.clone();
+
+        let oceans = (0..sea_areas.len())
+            .filter(|&region| sea_on_border[region])
+            .map(Region::Sea);
+        let border_islands = (0..island_seas.len())
+            .filter(|&label| island_on_border[label])
+            .map(Region::Island);
+        let mut queue: VecDeque<Region> = oceans.chain(border_islands).collect();
+
+        while let Some(region) = queue.pop_front() {
+            match region {
+                Region::Sea(region) => {
+                    for &label in sea_islands[region].iter() {
+                        if !visited_islands[label] {
+                            visited_islands[label] = true;
+                            queue.push_back(Region::Island(label));
+                        }
+                    }
+                }
+                Region::Island(label) => {
+                    for &region in island_seas[label].iter() {
+                        if !visited_seas[region] {
+                            visited_seas[region] = true;
+                            enclosing_islands[region] = Some(label);
+                            queue.push_back(Region::Sea(region));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut lake_labels: Vec<Option<Label>> = vec![None; sea_areas.len()];
+        let mut lakes: Vec<Lake> = vec![];
+
+        for region in 0..sea_areas.len() {
+            if sea_on_border[region] {
+                continue;
+            }
+
+            lake_labels[region] = Some(lakes.len());
+            lakes.push(Lake {
+                label: lakes.len(),
+                island: enclosing_islands[region],
+                area: sea_areas[region],
+            });
+        }
+
+        Lakes {
+            regions,
+            lake_labels,
+            lakes,
+        }
+    }
+
+    pub fn lakes(&self) -> &[Lake] {
+        &self.lakes
+    }
+
+    /// Label of the lake at `(x, y)`, `None` for land, open ocean or an invalid coordinate
+    pub fn lake_at(&self, coord: (usize, usize)) -> Option<Label> {
+        let region = self.regions.get(coord).flatten()?;
+        self.lake_labels[region]
+    }
+
+    /// `true` when `(x, y)` is a sea cell connected to the border of the map
+    pub fn is_ocean(&self, coord: (usize, usize)) -> bool {
+        let region = self.regions.get(coord).flatten();
+        region.is_some_and(|region| self.lake_labels[region].is_none())
+    }
+
+    /// Number of lakes enclosed by the island `label`
+    pub fn holes(&self, label: Label) -> usize {
+        self.lakes
+            .iter()
+            .filter(|lake| lake.island == Some(label))
+            .count()
+    }
+
+    /// Render `map` with `encoder`, lakes being rendered with `lake_encoder`
+    pub fn render(
+        &self,
+        map: &Map,
+        encoder: &dyn LabelEncoder,
+        lake_encoder: &dyn LabelEncoder,
+    ) -> String {
+        let format = map.format();
+
+        map.render_with(encoder.separator(), |coord, cell| {
+            match self.lake_at(coord) {
+                Some(lake) => lake_encoder
+                    .encode(lake)
+                    .unwrap_or_else(|| format.sea().to_string()),
+                None => encoder.encode_cell(cell, format),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::label::Alphabet;
+
+    const NESTED_MAP: &str = "\
+0000000  
+0     0  
+0 111 0 2
+0 1 1 0  
+0 111 0  
+0     0  
+0000000  
+";
+
+    #[test]
+    fn nested_lakes() {
+        let map: Map = NESTED_MAP.parse().unwrap();
+        let lakes = Lakes::new(&map, &Connectivity::four());
+
+        assert_eq!(
+            lakes.lakes(),
+            &[
+                Lake {
+                    label: 0,
+                    island: Some(0),
+                    area: 16,
+                },
+                Lake {
+                    label: 1,
+                    island: Some(1),
+                    area: 1,
+                },
+            ]
+        );
+        assert_eq!(lakes.holes(0), 1);
+        assert_eq!(lakes.holes(1), 1);
+        assert_eq!(lakes.holes(2), 0);
+
+        assert!(lakes.is_ocean((8, 0)));
+        assert!(!lakes.is_ocean((1, 1)));
+        assert!(!lakes.is_ocean((0, 0)));
+        assert_eq!(lakes.lake_at((1, 1)), Some(0));
+        assert_eq!(lakes.lake_at((3, 3)), Some(1));
+        assert_eq!(lakes.lake_at((8, 0)), None);
+    }
+
+    #[test]
+    fn diagonal_gap_is_not_a_lake() {
+        let map: Map = "000 \n0 0 \n00 0\n".parse().unwrap();
+
+        let four = Lakes::new(&map, &Connectivity::four());
+        assert_eq!(four.lakes(), &[]);
+
+        let eight = Lakes::new(&map, &Connectivity::eight());
+        assert_eq!(eight.lakes().len(), 1);
+        assert_eq!(eight.lakes()[0].island, Some(0));
+    }
+
+    #[test]
+    fn island_covering_the_border() {
+        let map: Map = "000\n0 0\n000\n".parse().unwrap();
+        let lakes = Lakes::new(&map, &Connectivity::four());
+
+        assert_eq!(lakes.holes(0), 1);
+        assert_eq!(lakes.lake_at((1, 1)), Some(0));
+    }
+
+    #[test]
+    fn render() {
+        let map: Map = NESTED_MAP.parse().unwrap();
+        let lakes = Lakes::new(&map, &Connectivity::four());
+        let rendered = lakes.render(&map, &Alphabet::BASE36, &Alphabet::GREEK);
+
+        assert_eq!(rendered.lines().nth(2), Some("0α111α0 2"));
+        assert_eq!(rendered.lines().nth(3), Some("0α1β1α0  "));
+    }
+}
@@ -1,98 +1,556 @@
-use super::cell::Cell;
-use super::cursor::Cursor;
+use super::cell::{Cell, CellKind};
+use super::cursor::{Cursor, Raster};
 use super::error::{Error, ErrorList};
-use std::cell::RefCell;
-use std::convert::TryFrom;
+use super::format::MapFormat;
+use super::grid::Grid;
+use super::label::{Label, LabelEncoder};
 use std::fmt;
+use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 
-type MapData = Vec<Vec<Cell>>;
-
-#[derive(Debug)]
-pub struct Map(RefCell<MapData>);
+/// # A parsed map
+///
+/// Cells are packed: a [`Grid`] of one byte [`CellKind`] per cell, the 32 bits labels of marked
+/// earth being kept in a separate buffer which is only allocated once a cell is marked, so a map
+/// costs one byte per cell, five once labelled. [`Map::get`], [`Map::write`] and cursors read and
+/// write whole [`Cell`]s, indexing by `(x, y)` reads and writes kinds. The map keeps the
+/// [`MapFormat`] it was parsed with, which is used to render it back
+///
+/// ```
+/// # use garm_playground::libs::island::{Cell, CellKind, Map};
+/// let mut map: Map = "# \n 0\n".parse().unwrap();
+/// map.write((0, 0), Cell::MarkedEarth(7));
+///
+/// assert_eq!(map.get((0, 0)), Some(Cell::MarkedEarth(7)));
+/// assert_eq!(map[(1, 1)], CellKind::MarkedEarth);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Map {
+    kinds: Grid<CellKind>,
+    // label of every cell, only meaningful for marked earth, empty while no cell is marked
+    labels: Vec<u32>,
+    format: MapFormat,
+}
 
 pub type MapResult<T = Map> = Result<T, ErrorList>;
 
+/// # How lines of different lengths are parsed
+///
+/// - `Pad` (the default): short lines are filled with sea up to the longest line
+/// - `Strict`: every line must have the length of the first one, see [`Error::RaggedLine`]
+/// - `Trim`: trailing whitespace is stripped from every line, which are then padded like `Pad`
+///
+/// Empty lines are always rejected, a parsed map is always rectangular.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RaggedLines {
+    #[default]
+    Pad,
+    Strict,
+    Trim,
+}
+
+/// # How a raw map is parsed
+///
+/// - `ragged_lines`: how lines of different lengths are parsed ([`RaggedLines::Pad`] by default)
+/// - `format`: the characters of sea, earth and marks ([`MapFormat::DEFAULT`] by default)
+/// - `lenient`: repair the map instead of rejecting it, see [`ParseOptions::lenient`]
+///   (`false` by default)
+/// - `max_errors`: give up once that many errors are found (unlimited by default)
+///
+/// ```
+/// # use garm_playground::libs::island::{ParseOptions, RaggedLines};
+/// let (map, warnings) = ParseOptions::new()
+///     .lenient()
+///     .ragged_lines(RaggedLines::Strict)
+///     .parse("##\n#!\n")
+///     .unwrap();
+///
+/// assert_eq!(map.to_string(), "##\n# \n");
+/// assert_eq!(warnings.0.len(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParseOptions {
+    pub(super) ragged_lines: RaggedLines,
+    pub(super) format: MapFormat,
+    lenient: bool,
+    pub(super) max_errors: Option<usize>,
+}
+
+impl ParseOptions {
+    /// Strict parsing with the default policy and format, every error being reported
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    pub fn ragged_lines(mut self, ragged_lines: RaggedLines) -> Self {
+        self.ragged_lines = ragged_lines;
+        self
+    }
+
+    pub fn format(mut self, format: MapFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Reject a map with any error, the default
+    pub fn strict(mut self) -> Self {
+        self.lenient = false;
+        self
+    }
+
+    /// Repair the map and report its errors as warnings: invalid characters and empty lines
+    /// become sea and ragged lines are padded. An empty map is still an error
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Give up on the first error, same as `max_errors(1)`
+    pub fn fail_fast(self) -> Self {
+        self.max_errors(1)
+    }
+
+    /// Give up once `max_errors` errors are found, in lenient mode too. `0` is treated as `1`
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = Some(max_errors.max(1));
+        self
+    }
+
+    /// Parse a raw map, and get its warnings: the errors repaired in lenient mode, none in
+    /// strict mode
+    pub fn parse(&self, s: &str) -> MapResult<(Map, ErrorList)> {
+        let format = self.format;
+        let kind = |c| format.cell(c).ok().map(|cell| cell.kind());
+        let (kinds, warnings) = self.parse_grid(s, kind, CellKind::Sea)?;
+        let mut map = Map {
+            kinds,
+            labels: vec![],
+            format,
+        };
+
+        // characters keep their position in the map, trimming and padding only change line ends
+        if map.kinds.cells().contains(&CellKind::MarkedEarth) {
+            for (y, line) in s.lines().enumerate() {
+                for (x, c) in line.chars().enumerate() {
+                    if let Some(label) = format.marks().label(c) {
+                        map.write((x, y), Cell::MarkedEarth(label));
+                    }
+                }
+            }
+        }
+
+        Ok((map, warnings))
+    }
+
+    /// Parse a raw grid where every character is a class of cells, e.g. for [`Regions`]. Cells
+    /// missing from short lines are `None`, and the format is not used: only empty and ragged
+    /// lines are errors
+    ///
+    /// [`Regions`]: super::Regions
+    pub fn parse_classes(&self, s: &str) -> MapResult<(Grid<Option<char>>, ErrorList)> {
+        self.parse_grid(s, |c| Some(Some(c)), None)
+    }
+
+    /// Parse a raw heightmap where every cell is a digit, e.g. for [`Sweep`]. Short lines are
+    /// padded with `0`, and so are the other characters in lenient mode
+    ///
+    /// [`Sweep`]: super::Sweep
+    pub fn parse_heights(&self, s: &str) -> MapResult<(Grid<u8>, ErrorList)> {
+        self.parse_grid(s, |c| c.to_digit(10).map(|digit| digit as u8), 0)
+    }
+
+    /// Parse a raw grid with `cell`, invalid characters (`None`) and missing cells becoming
+    /// `padding`
+    fn parse_grid<T, F>(&self, s: &str, cell: F, padding: T) -> MapResult<(Grid<T>, ErrorList)>
+    where
+        T: Clone,
+        F: Fn(char) -> Option<T>,
+    {
+        if s.is_empty() {
+            return Err(ErrorList(vec![Error::EmptyMap]));
+        }
+
+        let mut parser = LineParser::new(self.ragged_lines, self.format);
+        // one cell per character at most
+        let mut cells: Vec<T> = Vec::with_capacity(s.len());
+        let mut widths: Vec<usize> = vec![];
+
+        for line in s.lines() {
+            widths.push(parser.parse_with(line, &mut cells, &cell, &padding));
+
+            if let Some(max_errors) = self.max_errors {
+                if parser.errors.len() >= max_errors {
+                    parser.errors.truncate(max_errors);
+                    return Err(ErrorList(parser.errors));
+                }
+            }
+        }
+
+        if !self.lenient && !parser.errors.is_empty() {
+            return Err(ErrorList(parser.errors));
+        }
+
+        let grid = grid_with_widths(cells, &widths, padding);
+        Ok((grid, ErrorList(parser.errors)))
+    }
+}
+
 impl Map {
-    pub fn get(&self, (x, y): (usize, usize)) -> Option<Cell> {
-        let Map(data) = self;
-        return data.borrow().get(y)?.get(x).map(|cell| *cell);
+    /// Build a map from its lines of cells, short lines being padded with sea
+    pub fn with_format(lines: Vec<Vec<Cell>>, format: MapFormat) -> Map {
+        Map::from_grid(Grid::from_lines(lines, Cell::Sea), format)
     }
 
-    pub fn write(&self, (x, y): (usize, usize), new_cell: Cell) -> Option<()> {
-        let Map(data) = self;
+    pub fn from_grid(grid: Grid<Cell>, format: MapFormat) -> Map {
+        let cells = grid.cells();
+        let kinds = cells.iter().map(Cell::kind).collect();
+        let labels = match cells.iter().any(Cell::is_marked) {
+            true => cells
+                .iter()
+                .map(|cell| match *cell {
+                    Cell::MarkedEarth(label) => packed_label(label),
+                    _ => 0,
+                })
+                .collect(),
+            false => vec![],
+        };
 
-        data.borrow_mut()
-            .get_mut(y)?
-            .get_mut(x)
-            .map(|cell| *cell = new_cell)
+        Map {
+            kinds: Grid::new(grid.width(), grid.height(), kinds).expect("[FATAL] same size"),
+            labels,
+            format,
+        }
+    }
+
+    /// Number of lines
+    pub fn height(&self) -> usize {
+        self.kinds.height()
     }
 
-    pub fn cursor(&self) -> Cursor {
+    /// Number of cells of every line
+    pub fn width(&self) -> usize {
+        self.kinds.width()
+    }
+
+    /// Number of cells of the line `y`, `None` when the line does not exist
+    pub fn line_width(&self, y: usize) -> Option<usize> {
+        self.kinds.line_width(y)
+    }
+
+    /// `true` when `(x, y)` is inside of the map
+    pub fn contains(&self, coord: (usize, usize)) -> bool {
+        self.kinds.contains(coord)
+    }
+
+    pub fn get(&self, coord: (usize, usize)) -> Option<Cell> {
+        self.offset(coord).map(|i| self.cell(i))
+    }
+
+    /// Write a whole cell, its label included
+    ///
+    /// Panics when the label of a marked earth cell does not fit in 32 bits
+    pub fn write(&mut self, coord: (usize, usize), new_cell: Cell) -> Option<()> {
+        let i = self.offset(coord)?;
+        self.kinds.cells_mut()[i] = new_cell.kind();
+
+        if let Cell::MarkedEarth(label) = new_cell {
+            let (_, labels) = self.kinds_and_labels_mut();
+            labels[i] = packed_label(label);
+        }
+        Some(())
+    }
+
+    /// Every cell, line after line
+    pub fn cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..self.kinds.cells().len()).map(|i| self.cell(i))
+    }
+
+    /// The kind of every cell, e.g. for a [`Filler`](super::Filler) looking for earth
+    pub fn kinds(&self) -> &Grid<CellKind> {
+        &self.kinds
+    }
+
+    pub fn cursor(&self) -> Cursor<'_> {
         Cursor::new(self)
     }
-}
 
-impl FromStr for Map {
-    type Err = ErrorList;
+    /// Mark every cell labelled in `labels` with its label, e.g. the regions found by a
+    /// [`Filler`](super::Filler). `labels` must have the size of the map
+    pub fn mark(&mut self, labels: &Grid<Option<Label>>) {
+        let (kinds, packed_labels) = self.kinds_and_labels_mut();
+        let labels = labels.cells().iter();
 
-    fn from_str(s: &str) -> MapResult {
-        if s.len() == 0 {
-            return Err(ErrorList(vec![Error::EmptyMap]));
+        for ((kind, packed), label) in kinds.iter_mut().zip(packed_labels).zip(labels) {
+            if let Some(label) = *label {
+                *kind = CellKind::MarkedEarth;
+                *packed = packed_label(label);
+            }
+        }
+    }
+
+    /// Turn every marked earth cell back into earth
+    pub fn unmark(&mut self) {
+        for kind in self.kinds.cells_mut() {
+            if *kind == CellKind::MarkedEarth {
+                *kind = CellKind::Earth;
+            }
+        }
+    }
+
+    /// Replace the label of every marked earth cell with `relabel(label)`
+    pub fn relabel<F: FnMut(Label) -> Label>(&mut self, mut relabel: F) {
+        let (kinds, labels) = self.kinds_and_labels_mut();
+
+        for (kind, label) in kinds.iter().zip(labels) {
+            if *kind == CellKind::MarkedEarth {
+                *label = packed_label(relabel(*label as Label));
+            }
         }
+    }
+
+    /// Characters the map was parsed with
+    pub fn format(&self) -> &MapFormat {
+        &self.format
+    }
 
-        let mut errors: Vec<Error> = vec![];
+    /// Render the map with `encoder` used for the marked earth cells
+    pub fn render(&self, encoder: &dyn LabelEncoder) -> String {
+        let format = self.format();
+        self.render_with(encoder.separator(), |_, cell| {
+            encoder.encode_cell(cell, format)
+        })
+    }
 
-        let map_data: MapData = s
-            .lines()
-            .enumerate()
-            .map(|(index, line)| {
-                let line_number = index + 1;
+    /// Render the map with `encode` called on every `(x, y)` coordinate and its cell
+    pub fn render_with<F>(&self, separator: &str, encode: F) -> String
+    where
+        F: Fn((usize, usize), Cell) -> String,
+    {
+        let (width, height) = (self.width(), self.height());
+        let mut output = String::with_capacity(width * height * (1 + separator.len()));
 
-                if line.len() == 0 {
-                    errors.push(Error::EmptyLine(line_number))
+        for y in 0..height {
+            for x in 0..width {
+                if x > 0 {
+                    output += separator;
                 }
+                output += &encode((x, y), self.cell(y * width + x));
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Kinds and labels of every cell, line after line, the labels being allocated if needed.
+    /// A cell becoming marked earth must get its label written too
+    pub(super) fn kinds_and_labels_mut(&mut self) -> (&mut [CellKind], &mut [u32]) {
+        let len = self.kinds.cells().len();
+        if self.labels.len() != len {
+            self.labels = vec![0; len];
+        }
+
+        (self.kinds.cells_mut(), &mut self.labels)
+    }
+
+    /// Position of `(x, y)` in the buffers
+    fn offset(&self, (x, y): (usize, usize)) -> Option<usize> {
+        self.contains((x, y)).then(|| y * self.width() + x)
+    }
+
+    /// The cell at position `i` of the buffers
+    #[inline]
+    fn cell(&self, i: usize) -> Cell {
+        match self.kinds.cells()[i] {
+            CellKind::Sea => Cell::Sea,
+            CellKind::Earth => Cell::Earth,
+            CellKind::MarkedEarth => {
+                Cell::MarkedEarth(self.labels.get(i).map_or(0, |&l| l as Label))
+            }
+        }
+    }
+}
+
+/// A label as stored by a map, panics when it does not fit in 32 bits
+pub(super) fn packed_label(label: Label) -> u32 {
+    u32::try_from(label).unwrap_or_else(|_| panic!("[FATAL] label {} does not fit in a map", label))
+}
+
+/// Build a grid from its lines stored one after the other, `widths` being their lengths.
+/// Rectangular grids keep `cells` as is, short lines are padded otherwise
+fn grid_with_widths<T: Clone>(cells: Vec<T>, widths: &[usize], padding: T) -> Grid<T> {
+    let (width, height) = (widths.iter().copied().max().unwrap_or(0), widths.len());
+
+    if width * height == cells.len() {
+        return Grid::new(width, height, cells).expect("[FATAL] rectangular grid");
+    }
+
+    let mut lines = cells.into_iter();
+    let lines = widths
+        .iter()
+        .map(|&line_width| lines.by_ref().take(line_width).collect())
+        .collect();
+    Grid::from_lines(lines, padding)
+}
+
+/// # The kind of the cell at `(x, y)`
+///
+/// Panics when the coordinate is outside of the map. A cell made marked earth this way keeps the
+/// last label written to it, `0` by default: see [`Map::write`] to write a label
+impl Index<(usize, usize)> for Map {
+    type Output = CellKind;
+
+    fn index(&self, coord: (usize, usize)) -> &CellKind {
+        &self.kinds[coord]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Map {
+    fn index_mut(&mut self, coord: (usize, usize)) -> &mut CellKind {
+        &mut self.kinds[coord]
+    }
+}
+
+/// Maps are equal when they have the same format and the same cells, the labels of marked earth
+/// included
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.format == other.format && self.kinds == other.kinds && self.cells().eq(other.cells())
+    }
+}
+
+// inlined so that cursors of other crates read cells as fast as cursors of grids
+impl Raster for Map {
+    type Cell = Cell;
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.kinds.width()
+    }
+
+    #[inline]
+    fn height(&self) -> usize {
+        self.kinds.height()
+    }
+
+    #[inline]
+    fn read(&self, (x, y): (usize, usize)) -> Cell {
+        self.cell(y * self.width() + x)
+    }
+}
+
+/// Build a map with the default [`MapFormat`], see [`Map::with_format`]
+impl From<Vec<Vec<Cell>>> for Map {
+    fn from(lines: Vec<Vec<Cell>>) -> Self {
+        Map::with_format(lines, MapFormat::default())
+    }
+}
+
+/// Map of a grid of cells with the default [`MapFormat`]
+impl From<Grid<Cell>> for Map {
+    fn from(grid: Grid<Cell>) -> Self {
+        Map::from_grid(grid, MapFormat::default())
+    }
+}
 
-                line.chars()
-                    .enumerate()
-                    .map(|(i, c)| {
-                        // on parse error: notify InvalidChar errors and replace map cell by Sea
-                        Cell::try_from(c).unwrap_or_else(|_err| {
-                            errors.push(Error::InvalidChar {
-                                char: c,
-                                line: line_number,
-                                col: i + 1,
-                            });
-                            return Cell::Sea;
-                        })
-                    })
-                    .collect()
+/// # Parse a raw map line by line
+///
+/// Errors are accumulated in `errors`, invalid characters being replaced by sea in a map
+pub(super) struct LineParser {
+    ragged_lines: RaggedLines,
+    format: MapFormat,
+    line_number: usize,
+    expected_width: Option<usize>,
+    pub errors: Vec<Error>,
+}
+
+impl LineParser {
+    pub fn new(ragged_lines: RaggedLines, format: MapFormat) -> Self {
+        LineParser {
+            ragged_lines,
+            format,
+            line_number: 0,
+            expected_width: None,
+            errors: vec![],
+        }
+    }
+
+    pub fn parse(&mut self, line: &str) -> Vec<Cell> {
+        let format = self.format;
+        let mut cells = vec![];
+        self.parse_with(line, &mut cells, |c| format.cell(c).ok(), &Cell::Sea);
+        cells
+    }
+
+    /// Parse a line of any cell type with `cell`, invalid characters (`None`) becoming `invalid`.
+    /// The cells are appended to `cells`, gives the line width
+    pub fn parse_with<T, F>(
+        &mut self,
+        line: &str,
+        cells: &mut Vec<T>,
+        cell: F,
+        invalid: &T,
+    ) -> usize
+    where
+        T: Clone,
+        F: Fn(char) -> Option<T>,
+    {
+        self.line_number += 1;
+        let line_number = self.line_number;
+
+        if line.is_empty() {
+            self.errors.push(Error::EmptyLine(line_number))
+        }
+
+        let line = match self.ragged_lines {
+            RaggedLines::Trim => line.trim_end(),
+            _ => line,
+        };
+
+        let start = cells.len();
+        cells.extend(line.chars().enumerate().map(|(i, c)| {
+            // on parse error: notify InvalidChar errors and replace the cell
+            cell(c).unwrap_or_else(|| {
+                self.errors.push(Error::InvalidChar {
+                    char: c,
+                    line: line_number,
+                    col: i + 1,
+                });
+                invalid.clone()
             })
-            .collect();
+        }));
+        let width = cells.len() - start;
+
+        if self.ragged_lines == RaggedLines::Strict && width > 0 {
+            let expected = *self.expected_width.get_or_insert(width);
 
-        if errors.len() == 0 {
-            Ok(Map(RefCell::new(map_data)))
-        } else {
-            Err(ErrorList(errors))
+            if width != expected {
+                self.errors.push(Error::RaggedLine {
+                    line: line_number,
+                    expected,
+                    found: width,
+                });
+            }
         }
+
+        width
+    }
+}
+
+/// Parse a raw map with the default [`ParseOptions`]
+impl FromStr for Map {
+    type Err = ErrorList;
+
+    fn from_str(s: &str) -> MapResult {
+        let (map, _) = ParseOptions::new().parse(s)?;
+        Ok(map)
     }
 }
 
+/// Render the map with its own [`MapFormat`], marks included
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Map(data) = self;
-        let raw_map: String = data
-            .borrow()
-            .iter()
-            .map(|line| {
-                line.iter()
-                    .map(|cell| char::from(*cell))
-                    .collect::<String>()
-                    + "\n"
-            })
-            .collect();
-
-        write!(f, "{}", raw_map)
+        write!(f, "{}", self.render(&self.format().marks()))
     }
 }
 
@@ -100,6 +558,11 @@ impl fmt::Display for Map {
 mod tests {
     use super::*;
 
+    fn parse(s: &str, options: ParseOptions) -> MapResult {
+        let (map, _) = options.parse(s)?;
+        Ok(map)
+    }
+
     const RAW_VALID_MAP: &str = "\
 #  
 ###
@@ -113,15 +576,18 @@ mod tests {
 
         #[test]
         fn valid_map() -> MapResult<()> {
-            let Map(map_data) = RAW_VALID_MAP.parse()?;
+            let map: Map = RAW_VALID_MAP.parse()?;
+
+            assert_eq!((map.width(), map.height()), (3, 4));
             assert_eq!(
-                *map_data.borrow(),
-                vec![
-                    vec![Earth, Sea, Sea],
-                    vec![Earth, Earth, Earth],
-                    vec![Sea, Earth, Sea],
-                    vec![Earth, Sea, MarkedEarth('0')],
+                map.cells().collect::<Vec<_>>(),
+                [
+                    [Earth, Sea, Sea],
+                    [Earth, Earth, Earth],
+                    [Sea, Earth, Sea],
+                    [Earth, Sea, MarkedEarth(0)],
                 ]
+                .concat()
             );
             Ok(())
         }
@@ -200,6 +666,254 @@ mod tests {
         }
     }
 
+    mod ragged_lines {
+        use super::*;
+
+        const RAGGED_MAP: &str = "##  \n#\n  #   \n";
+
+        #[test]
+        fn pad_by_default() {
+            let map: Map = RAGGED_MAP.parse().unwrap();
+            assert_eq!(map.to_string(), "##    \n#     \n  #   \n");
+        }
+
+        #[test]
+        fn pad() -> MapResult<()> {
+            let map = parse(
+                RAGGED_MAP,
+                ParseOptions::new().ragged_lines(RaggedLines::Pad),
+            )?;
+            assert_eq!(map.to_string(), "##    \n#     \n  #   \n");
+            Ok(())
+        }
+
+        #[test]
+        fn trim() -> MapResult<()> {
+            let map = parse(
+                RAGGED_MAP,
+                ParseOptions::new().ragged_lines(RaggedLines::Trim),
+            )?;
+            assert_eq!(map.to_string(), "## \n#  \n  #\n");
+            Ok(())
+        }
+
+        #[test]
+        fn trim_sea_line() -> MapResult<()> {
+            let map = parse(
+                "#\n   \n#\n",
+                ParseOptions::new().ragged_lines(RaggedLines::Trim),
+            )?;
+            assert_eq!(map.to_string(), "#\n \n#\n");
+            Ok(())
+        }
+
+        #[test]
+        fn strict() {
+            let result = parse(
+                RAGGED_MAP,
+                ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            );
+
+            assert_eq!(
+                result.unwrap_err(),
+                ErrorList(vec![
+                    Error::RaggedLine {
+                        line: 2,
+                        expected: 4,
+                        found: 1
+                    },
+                    Error::RaggedLine {
+                        line: 3,
+                        expected: 4,
+                        found: 6
+                    },
+                ])
+            );
+        }
+
+        #[test]
+        fn strict_rectangular() -> MapResult<()> {
+            let map = parse(
+                RAW_VALID_MAP,
+                ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            )?;
+            assert_eq!(map.to_string(), RAW_VALID_MAP);
+            Ok(())
+        }
+
+        #[test]
+        fn strict_empty_line() {
+            let result = parse(
+                "##\n\n#\n",
+                ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            );
+
+            assert_eq!(
+                result.unwrap_err(),
+                ErrorList(vec![
+                    Error::EmptyLine(2),
+                    Error::RaggedLine {
+                        line: 3,
+                        expected: 2,
+                        found: 1
+                    },
+                ])
+            );
+        }
+    }
+
+    mod parse_options {
+        use super::*;
+
+        const BROKEN_MAP: &str = "##\n\n#!\n#[]\n";
+
+        #[test]
+        fn strict_by_default() {
+            let result = ParseOptions::new().parse(BROKEN_MAP);
+            assert_eq!(result.unwrap_err().0.len(), 4);
+        }
+
+        #[test]
+        fn lenient() -> MapResult<()> {
+            let (map, ErrorList(warnings)) = ParseOptions::new().lenient().parse(BROKEN_MAP)?;
+
+            assert_eq!(map.to_string(), "## \n   \n#  \n#  \n");
+            assert_eq!(
+                warnings,
+                vec![
+                    Error::EmptyLine(2),
+                    Error::InvalidChar {
+                        char: '!',
+                        line: 3,
+                        col: 2
+                    },
+                    Error::InvalidChar {
+                        char: '[',
+                        line: 4,
+                        col: 2
+                    },
+                    Error::InvalidChar {
+                        char: ']',
+                        line: 4,
+                        col: 3
+                    },
+                ]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn lenient_valid_map() -> MapResult<()> {
+            let (map, warnings) = ParseOptions::new().lenient().parse(RAW_VALID_MAP)?;
+
+            assert_eq!(map.to_string(), RAW_VALID_MAP);
+            assert_eq!(warnings, ErrorList(vec![]));
+            Ok(())
+        }
+
+        #[test]
+        fn lenient_ragged_lines() -> MapResult<()> {
+            let options = ParseOptions::new()
+                .ragged_lines(RaggedLines::Strict)
+                .lenient();
+            let (map, ErrorList(warnings)) = options.parse("###\n#\n")?;
+
+            assert_eq!(map.to_string(), "###\n#  \n");
+            assert_eq!(warnings.len(), 1);
+            Ok(())
+        }
+
+        #[test]
+        fn lenient_empty_map() {
+            let result = ParseOptions::new().lenient().parse("");
+            assert_eq!(result.unwrap_err(), ErrorList(vec![Error::EmptyMap]));
+        }
+
+        #[test]
+        fn fail_fast() {
+            let result = ParseOptions::new().fail_fast().parse(BROKEN_MAP);
+            assert_eq!(result.unwrap_err(), ErrorList(vec![Error::EmptyLine(2)]));
+        }
+
+        #[test]
+        fn max_errors() {
+            let result = ParseOptions::new().max_errors(3).parse(BROKEN_MAP);
+            let ErrorList(errors) = result.unwrap_err();
+
+            // the errors of a line are truncated
+            assert_eq!(errors.len(), 3);
+            assert_eq!(
+                errors[2],
+                Error::InvalidChar {
+                    char: '[',
+                    line: 4,
+                    col: 2
+                }
+            );
+        }
+
+        #[test]
+        fn max_errors_not_reached() {
+            let result = ParseOptions::new().max_errors(5).parse(BROKEN_MAP);
+            assert_eq!(result.unwrap_err().0.len(), 4);
+        }
+
+        #[test]
+        fn lenient_gives_up_at_max_errors() {
+            let result = ParseOptions::new()
+                .lenient()
+                .max_errors(2)
+                .parse(BROKEN_MAP);
+            assert_eq!(result.unwrap_err().0.len(), 2);
+
+            let result = ParseOptions::new()
+                .lenient()
+                .max_errors(5)
+                .parse(BROKEN_MAP);
+            assert!(result.is_ok());
+        }
+
+        #[test]
+        fn strict_overrides_lenient() {
+            let options = ParseOptions::new().lenient().strict();
+            assert!(options.parse(BROKEN_MAP).is_err());
+        }
+
+        #[test]
+        fn classes() -> MapResult<()> {
+            let (grid, warnings) = ParseOptions::new().parse_classes("ab!\nc\n")?;
+
+            assert_eq!(
+                grid.cells(),
+                [Some('a'), Some('b'), Some('!'), Some('c'), None, None]
+            );
+            assert!(warnings.0.is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn classes_errors() {
+            let strict = ParseOptions::new().ragged_lines(RaggedLines::Strict);
+
+            assert_eq!(
+                strict.parse_classes("ab\n\nc\n").unwrap_err().0,
+                vec![
+                    Error::EmptyLine(2),
+                    Error::RaggedLine {
+                        line: 3,
+                        expected: 2,
+                        found: 1
+                    }
+                ]
+            );
+            assert_eq!(
+                strict.lenient().parse_classes("ab\nc\n").unwrap().0.cells(),
+                [Some('a'), Some('b'), Some('c'), None]
+            );
+            assert!(ParseOptions::new().parse_classes("").is_err());
+        }
+    }
+
     mod display {
         use super::*;
 
@@ -226,6 +940,108 @@ mod tests {
         }
     }
 
+    mod render {
+        use super::*;
+        use crate::libs::island::label::{Alphabet, Csv};
+
+        #[test]
+        fn base36_is_display() {
+            let map: Map = RAW_VALID_MAP.parse().unwrap();
+            assert_eq!(map.render(&Alphabet::BASE36), map.to_string());
+        }
+
+        #[test]
+        fn large_labels() {
+            let mut map: Map = "# #\n".parse().unwrap();
+            map.write((0, 0), Cell::MarkedEarth(61));
+            map.write((2, 0), Cell::MarkedEarth(4242));
+
+            assert_eq!(map.render(&Alphabet::BASE36), "# #\n");
+            assert_eq!(map.render(&Alphabet::BASE62), "Z #\n");
+            assert_eq!(map.render(&Csv), "61,,4242\n");
+        }
+    }
+
+    mod map_format {
+        use super::*;
+        use crate::libs::island::label::Alphabet;
+
+        #[test]
+        fn parse_and_display() -> MapResult<()> {
+            let raw_map = "X..\n.XX\n5.X\n";
+            let map = parse(
+                raw_map,
+                ParseOptions::new()
+                    .ragged_lines(RaggedLines::Pad)
+                    .format(MapFormat::DOTS),
+            )?;
+
+            assert_eq!(map.get((0, 0)), Some(Cell::Earth));
+            assert_eq!(map.get((1, 0)), Some(Cell::Sea));
+            assert_eq!(map.get((0, 2)), Some(Cell::MarkedEarth(5)));
+            assert_eq!(map.to_string(), raw_map);
+            Ok(())
+        }
+
+        #[test]
+        fn render() -> MapResult<()> {
+            let mut map = parse(
+                "0110\n",
+                ParseOptions::new()
+                    .ragged_lines(RaggedLines::Pad)
+                    .format(MapFormat::BINARY),
+            )?;
+            map.write((1, 0), Cell::MarkedEarth(11));
+
+            assert_eq!(map.to_string(), "0110\n");
+            // base36 marks would be read back as sea and earth
+            assert!(Alphabet::BASE36.collides(map.format()));
+            assert_eq!(map.render(&Alphabet::GREEK), "0μ10\n");
+            Ok(())
+        }
+
+        #[test]
+        fn default_chars_are_invalid() {
+            let result = parse(
+                "# \n",
+                ParseOptions::new()
+                    .ragged_lines(RaggedLines::Pad)
+                    .format(MapFormat::DOTS),
+            );
+
+            assert_eq!(
+                result.unwrap_err(),
+                ErrorList(vec![
+                    Error::InvalidChar {
+                        char: '#',
+                        line: 1,
+                        col: 1
+                    },
+                    Error::InvalidChar {
+                        char: ' ',
+                        line: 1,
+                        col: 2
+                    },
+                ])
+            );
+        }
+    }
+
+    mod dimensions {
+        use super::*;
+
+        #[test]
+        fn ragged_map() {
+            let map: Map = "#\n###\n ".parse().unwrap();
+
+            assert_eq!(map.height(), 3);
+            assert_eq!(map.line_width(0), Some(3));
+            assert_eq!(map.line_width(1), Some(3));
+            assert_eq!(map.line_width(2), Some(3));
+            assert_eq!(map.line_width(3), None);
+        }
+    }
+
     mod get {
         use super::*;
         use Cell::*;
@@ -246,7 +1062,7 @@ mod tests {
             // entire line 3
             assert_eq!(map.get((0, 3)), Some(Earth));
             assert_eq!(map.get((1, 3)), Some(Sea));
-            assert_eq!(map.get((2, 3)), Some(MarkedEarth('0')));
+            assert_eq!(map.get((2, 3)), Some(MarkedEarth(0)));
         }
 
         #[test]
@@ -265,7 +1081,7 @@ mod tests {
 
         #[test]
         fn simple_write() {
-            let map: Map = "000\n".parse().unwrap();
+            let mut map: Map = "000\n".parse().unwrap();
 
             assert_eq!("000\n", map.to_string());
             assert_eq!(Some(()), map.write((0, 0), Cell::Earth));
@@ -274,7 +1090,7 @@ mod tests {
 
         #[test]
         fn multiple_write() {
-            let map: Map = "00\n00\n".parse().unwrap();
+            let mut map: Map = "00\n00\n".parse().unwrap();
 
             assert_eq!("00\n00\n", map.to_string());
 
@@ -284,20 +1100,161 @@ mod tests {
             assert_eq!(Some(()), map.write((1, 0), Cell::Sea));
             assert_eq!("# \n00\n", map.to_string());
 
-            assert_eq!(Some(()), map.write((0, 1), Cell::MarkedEarth('1')));
+            assert_eq!(Some(()), map.write((0, 1), Cell::MarkedEarth(1)));
             assert_eq!("# \n10\n", map.to_string());
 
-            assert_eq!(Some(()), map.write((1, 1), Cell::MarkedEarth('2')));
+            assert_eq!(Some(()), map.write((1, 1), Cell::MarkedEarth(2)));
             assert_eq!("# \n12\n", map.to_string());
         }
 
         #[test]
         fn invalid_write() {
-            let map: Map = "000\n".parse().unwrap();
+            let mut map: Map = "000\n".parse().unwrap();
 
             assert_eq!("000\n", map.to_string());
             assert_eq!(None, map.write((0, 1), Cell::Earth));
             assert_eq!("000\n", map.to_string());
         }
     }
+
+    mod index {
+        use super::*;
+        use Cell::*;
+
+        #[test]
+        fn index() {
+            let map: Map = RAW_VALID_MAP.parse().unwrap();
+
+            assert_eq!(map[(0, 0)], CellKind::Earth);
+            assert_eq!(map[(1, 0)], CellKind::Sea);
+            assert_eq!(map[(2, 3)], CellKind::MarkedEarth);
+        }
+
+        #[test]
+        fn index_mut() {
+            let mut map: Map = "00\n00\n".parse().unwrap();
+            map.write((0, 1), MarkedEarth(3));
+
+            map[(1, 0)] = CellKind::Earth;
+            map[(0, 1)] = CellKind::Sea;
+            assert_eq!(map.to_string(), "0#\n 0\n");
+
+            // the kind is written alone, the cell keeps its last label
+            map[(0, 1)] = CellKind::MarkedEarth;
+            assert_eq!(map.to_string(), "0#\n30\n");
+        }
+
+        #[test]
+        #[should_panic(expected = "invalid grid index x:3 y:0")]
+        fn index_outside() {
+            let map: Map = RAW_VALID_MAP.parse().unwrap();
+            let _ = map[(3, 0)];
+        }
+
+        #[test]
+        fn padded_lines() {
+            let map: Map = "#\n###\n".parse().unwrap();
+
+            assert_eq!(
+                map.cells().collect::<Vec<_>>(),
+                [Earth, Sea, Sea, Earth, Earth, Earth]
+            );
+            assert_eq!(map.get((2, 0)), Some(Sea));
+        }
+    }
+
+    mod packed {
+        use super::*;
+        use Cell::*;
+
+        #[test]
+        fn labels_stored_apart() {
+            let mut map: Map = "# \n##\n".parse().unwrap();
+            assert!(map.labels.is_empty());
+
+            map.write((1, 1), MarkedEarth(70_000));
+            assert_eq!(map.labels.len(), 4);
+            assert_eq!(map.get((1, 1)), Some(MarkedEarth(70_000)));
+            assert_eq!(map.kinds().cells()[3], CellKind::MarkedEarth);
+        }
+
+        #[test]
+        fn stale_labels_are_not_compared() {
+            let mut map: Map = "0#\n".parse().unwrap();
+            map.write((0, 0), Earth);
+
+            assert_eq!(map, "##\n".parse().unwrap());
+        }
+
+        #[test]
+        #[cfg(target_pointer_width = "64")]
+        #[should_panic(expected = "does not fit in a map")]
+        fn label_too_large() {
+            let mut map: Map = "#\n".parse().unwrap();
+            map.write((0, 0), MarkedEarth(u32::MAX as Label + 1));
+        }
+
+        #[test]
+        fn unmark() {
+            let mut map: Map = "0 1\n# 1\n".parse().unwrap();
+            map.unmark();
+
+            assert_eq!(map.to_string(), "# #\n# #\n");
+        }
+
+        #[test]
+        fn relabel() {
+            let mut map: Map = "0 1\n# 1\n".parse().unwrap();
+            map.relabel(|label| 10 - label);
+
+            assert_eq!(map.to_string(), "a 9\n# 9\n");
+        }
+    }
+
+    mod clone {
+        use super::*;
+
+        #[test]
+        fn clone_is_independent() {
+            let map: Map = RAW_VALID_MAP.parse().unwrap();
+            let mut copy = map.clone();
+
+            assert_eq!(copy, map);
+            copy[(1, 0)] = CellKind::Earth;
+            assert_ne!(copy, map);
+            assert_eq!(map.get((1, 0)), Some(Cell::Sea));
+        }
+
+        #[test]
+        fn format_is_compared() -> MapResult<()> {
+            let binary = parse("10\n", ParseOptions::new().format(MapFormat::BINARY))?;
+            let default: Map = "# \n".parse()?;
+
+            assert!(binary.cells().eq(default.cells()));
+            assert_ne!(binary, default);
+            Ok(())
+        }
+    }
+
+    mod mark {
+        use super::*;
+
+        #[test]
+        fn mark_labelled_cells() {
+            let mut map: Map = "# #\n## \n".parse().unwrap();
+            let labels = Grid::new(3, 2, vec![Some(0), None, Some(1), Some(0), Some(0), None]);
+
+            map.mark(&labels.unwrap());
+            assert_eq!(map.to_string(), "0 1\n00 \n");
+        }
+
+        #[test]
+        fn grid_of_a_map() {
+            let grid = Grid::filled(2, 1, Cell::Earth);
+            let map = Map::from(grid.clone());
+
+            assert_eq!(map.cells().collect::<Vec<_>>(), grid.cells());
+            assert_eq!(map.to_string(), "##\n");
+        }
+    }
 }
@@ -1,34 +1,225 @@
-use self::filler::Filler;
-
 mod cell;
+mod connectivity;
 mod cursor;
+mod diagnostics;
+mod disjoint_set;
+mod dynamic;
 mod error;
 mod filler;
+mod format;
+mod generator;
+mod grid;
+mod heightmap;
+mod json;
+mod label;
+mod labeler;
+mod lakes;
 mod map;
+mod netpbm;
+mod preserve;
+mod regions;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
+mod stream;
+mod verify;
 
+pub use cell::{Cell, CellKind};
+pub use connectivity::Connectivity;
+pub use cursor::{Cursor, CursorIter, Direction, Raster};
+pub use diagnostics::Diagnostics;
+pub use disjoint_set::DisjointSet;
+pub use dynamic::DynamicMap;
 pub use error::*;
+pub use filler::Filler;
+pub use format::{map_format, MapFormat, MAP_FORMAT_NAMES};
+pub use generator::{terrain, Generator, Random, Terrain, TERRAIN_NAMES};
+pub use grid::Grid;
+pub use heightmap::{land, MergeNode, Sweep, WaterLevel};
+pub use json::{report, Json};
+pub use label::*;
+pub use labeler::*;
+pub use lakes::{Lake, Lakes};
+pub use map::{Map, ParseOptions, RaggedLines};
+pub use netpbm::{island_colour, read_netpbm, write_pbm, write_ppm, NetpbmEncoding, NetpbmError};
+pub use preserve::PreserveMarks;
+pub use regions::{ClassRegions, Regions};
+pub use stats::{BoundingBox, IslandStats, Stats};
+pub use stream::{StreamError, StreamLabeler};
+
+use std::io::{BufRead, Seek, SeekFrom, Write};
 
 /// The result returned by [`run`]
 pub type AppResult<T = String> = map::MapResult<T>;
 
+/// # Options used by [`run_with`]
+///
+/// - `encoder`: how island labels are rendered in the output map (base36 by default)
+/// - `connectivity`: which neighbours belong to the same island (4-connectivity by default)
+/// - `lake_encoder`: when set, how [lakes](Lake) are rendered in the output map
+/// - `labeler`: the connected-components engine ([`FloodFill`] by default)
+/// - `parse`: how the raw map is parsed, its [`MapFormat`] being also used for the output map
+///   (strict [`ParseOptions`] by default)
+/// - `preserve_marks`: keep the labels already on the map instead of renumbering every island,
+///   see [`PreserveMarks`] (`false` by default)
+pub struct Options {
+    pub encoder: Box<dyn LabelEncoder>,
+    pub connectivity: Connectivity,
+    pub lake_encoder: Option<Box<dyn LabelEncoder>>,
+    pub labeler: Box<dyn Labeler>,
+    pub parse: ParseOptions,
+    pub preserve_marks: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            encoder: Box::new(Alphabet::BASE36),
+            connectivity: Connectivity::default(),
+            lake_encoder: None,
+            labeler: Box::new(FloodFill),
+            parse: ParseOptions::default(),
+            preserve_marks: false,
+        }
+    }
+}
+
 /// Apply the count_island logic on a raw map and get a new raw map wrapped in a [`AppResult`]
 pub fn run(raw_map: &str) -> AppResult {
-    let filler = Filler::new(raw_map.parse()?);
-    let map = &filler.map;
-    let cursor = map.cursor();
+    run_with(raw_map, &Options::default())
+}
 
-    let marked_cursor = cursor.iter().filter(|c| c.read().is_marked());
-    let markable_cursors = cursor.iter().filter(|c| c.read().is_markable());
+/// Same as [`run`] with custom [`Options`]
+///
+/// The warnings of a lenient parsing are dropped, see [`run_with_warnings`]
+pub fn run_with(raw_map: &str, options: &Options) -> AppResult {
+    let (raw_map, _) = run_with_warnings(raw_map, options)?;
 
-    for cursor in marked_cursor {
-        map.write(cursor.coord(), cell::Cell::Earth);
-    }
+    Ok(raw_map)
+}
+
+/// Same as [`run_with`], with the errors repaired by a lenient parsing, see
+/// [`ParseOptions::lenient`]
+pub fn run_with_warnings(raw_map: &str, options: &Options) -> AppResult<(String, ErrorList)> {
+    let (map, warnings) = label(raw_map, options)?;
+    let encoder = options.encoder.as_ref();
 
-    for cursor in markable_cursors {
-        if filler.fill(cursor).is_none() {
-            break;
+    let raw_map = match &options.lake_encoder {
+        Some(lake_encoder) => {
+            let lakes = Lakes::new(&map, &options.connectivity);
+            lakes.render(&map, encoder, lake_encoder.as_ref())
         }
+        None => map.render(encoder),
+    };
+
+    Ok((raw_map, warnings))
+}
+
+/// Label a raw map and get the [`Stats`] of every island
+pub fn stats(raw_map: &str, options: &Options) -> AppResult<Stats> {
+    let (map, _) = label(raw_map, options)?;
+
+    Ok(Stats::new(&map, &options.connectivity))
+}
+
+/// Label the regions of every class of a raw grid, every character being a class, see
+/// [`ParseOptions::parse_classes`]
+///
+/// Only `connectivity` and `parse` are used from `options`
+pub fn regions(raw_map: &str, options: &Options) -> AppResult<Regions<char>> {
+    let (grid, _) = options.parse.parse_classes(raw_map)?;
+
+    Ok(Regions::partial(grid, &options.connectivity))
+}
+
+/// Sweep the water level over a raw heightmap, see [`ParseOptions::parse_heights`]
+///
+/// Only `connectivity` and `parse` are used from `options`
+pub fn sweep(raw_map: &str, options: &Options) -> AppResult<Sweep> {
+    let (heights, _) = options.parse.parse_heights(raw_map)?;
+
+    Ok(Sweep::new(&heights, &options.connectivity))
+}
+
+/// Label the islands of a raw heightmap above the water `level`
+pub fn run_at_level(raw_map: &str, level: u8, options: &Options) -> AppResult {
+    let (heights, _) = options.parse.parse_heights(raw_map)?;
+    let map = label_map(land(&heights, level), options);
+
+    Ok(map.render(options.encoder.as_ref()))
+}
+
+/// Label a raw map and get its [`Lakes`]
+pub fn lakes(raw_map: &str, options: &Options) -> AppResult<Lakes> {
+    let (map, _) = label(raw_map, options)?;
+
+    Ok(Lakes::new(&map, &options.connectivity))
+}
+
+/// Label a raw map and get a JSON report of its islands, see [`report`] for the schema
+///
+/// Parse errors are converted with `Json::from(&errors)`, which gives the same schema. The errors
+/// repaired by a lenient parsing are reported as warnings
+pub fn json(raw_map: &str, options: &Options) -> AppResult<Json> {
+    let (map, warnings) = label(raw_map, options)?;
+    let stats = Stats::new(&map, &options.connectivity);
+
+    Ok(json::report(
+        &map,
+        &stats,
+        options.encoder.as_ref(),
+        &warnings,
+    ))
+}
+
+/// Check the labels of an already labelled raw map, see [`Error`] for the reported problems
+pub fn verify(raw_map: &str, options: &Options) -> AppResult<()> {
+    let (map, _) = options.parse.parse(raw_map)?;
+
+    verify::verify(map, options.labeler.as_ref(), &options.connectivity)
+}
+
+/// Count the islands of a raw map read line by line, see [`StreamLabeler`]
+///
+/// Only `connectivity` and `parse` are used from `options`, the map being always parsed strictly
+pub fn count_stream<R: BufRead>(reader: R, options: &Options) -> Result<usize, StreamError> {
+    let labeler = StreamLabeler::scan(reader, &options.connectivity, &options.parse)?;
+
+    Ok(labeler.island_count())
+}
+
+/// Same as [`run_with`] on a raw map read line by line and written to `writer`,
+/// see [`StreamLabeler`]
+///
+/// The input is read twice. Only `encoder`, `connectivity` and `parse` are used from `options`,
+/// the map being always parsed strictly
+pub fn run_stream<R, W>(mut reader: R, writer: W, options: &Options) -> Result<(), StreamError>
+where
+    R: BufRead + Seek,
+    W: Write,
+{
+    let labeler = StreamLabeler::scan(&mut reader, &options.connectivity, &options.parse)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    labeler.relabel(reader, writer, options.encoder.as_ref())
+}
+
+/// Label an already parsed map, e.g. read with [`read_netpbm`]
+///
+/// Only `labeler`, `connectivity` and `preserve_marks` are used from `options`
+pub fn label_map(mut map: Map, options: &Options) -> Map {
+    let labeler = options.labeler.as_ref();
+
+    if options.preserve_marks {
+        return PreserveMarks(labeler).label(map, &options.connectivity);
     }
 
-    Ok(map.to_string())
+    map.unmark();
+    labeler.label(map, &options.connectivity)
+}
+
+fn label(raw_map: &str, options: &Options) -> AppResult<(Map, ErrorList)> {
+    let (map, warnings) = options.parse.parse(raw_map)?;
+
+    Ok((label_map(map, options), warnings))
 }
@@ -0,0 +1,517 @@
+use super::cell::Cell;
+use super::label::Label;
+use super::map::Map;
+use std::fmt;
+
+/// # Netpbm flavour written by [`write_pbm`] and [`write_ppm`]
+///
+/// - `Plain`: ASCII samples (`P1`, `P3`)
+/// - `Raw` (the default): binary samples (`P4`, `P6`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NetpbmEncoding {
+    Plain,
+    #[default]
+    Raw,
+}
+
+/*
+ * Reading
+ */
+
+/// # Read a Netpbm bitmap or greymap into a [`Map`]
+///
+/// Accepts `P1`/`P4` bitmaps and `P2`/`P5` greymaps. Both kinds of image are read the way they
+/// are usually drawn, so earth is black on a bitmap but bright on a greymap:
+///
+/// - bitmaps are ink on paper: `1` (black) pixels are earth, as written by [`write_pbm`]
+/// - greymaps are elevations: samples at or above `threshold` (bright) are earth. The threshold
+///   defaults to the half of the maximum value, e.g. `128` out of `255`
+///
+/// Only the first image of the file is read. Dimensions needing more samples than the rest of
+/// the file holds give a [`NetpbmError::Truncated`].
+pub fn read_netpbm(bytes: &[u8], threshold: Option<u16>) -> Result<Map, NetpbmError> {
+    let mut reader = Reader { bytes, pos: 0 };
+
+    let (greymap, raw) = match reader.take(2) {
+        Some(b"P1") => (false, false),
+        Some(b"P2") => (true, false),
+        Some(b"P4") => (false, true),
+        Some(b"P5") => (true, true),
+        _ => return Err(NetpbmError::Magic),
+    };
+
+    let width = reader.number("width")? as usize;
+    let height = reader.number("height")? as usize;
+    let maxval = match greymap {
+        true => reader.number("maxval")?,
+        false => 1,
+    };
+
+    if width == 0 || height == 0 {
+        return Err(NetpbmError::Empty);
+    }
+    if maxval == 0 || maxval > u16::MAX as u32 {
+        return Err(NetpbmError::Header("maxval"));
+    }
+    if raw && !reader.byte().is_some_and(|b| b.is_ascii_whitespace()) {
+        return Err(NetpbmError::Header("raster"));
+    }
+
+    // smallest raster of the image, so that a header cannot make us allocate more than the file
+    let raster_len = match (greymap, raw) {
+        (false, true) => width.div_ceil(8).checked_mul(height),
+        (true, true) if maxval > 255 => width.checked_mul(height).and_then(|n| n.checked_mul(2)),
+        _ => width.checked_mul(height),
+    };
+    if raster_len.is_none_or(|len| len > reader.remaining()) {
+        return Err(NetpbmError::Truncated);
+    }
+
+    let threshold = threshold.map_or(maxval / 2 + 1, u32::from);
+    let mut lines: Vec<Vec<Cell>> = Vec::with_capacity(height);
+
+    for y in 0..height {
+        let mut line: Vec<Cell> = Vec::with_capacity(width);
+
+        for x in 0..width {
+            let sample = match (greymap, raw) {
+                (false, false) => reader.plain_bit(),
+                (true, false) => reader.plain_sample(),
+                (false, true) => reader.raw_bit(x, width),
+                (true, true) => reader.raw_sample(maxval),
+            }?;
+
+            let earth = match greymap {
+                true if sample > maxval => {
+                    return Err(NetpbmError::Sample {
+                        line: y + 1,
+                        col: x + 1,
+                    })
+                }
+                true => sample >= threshold,
+                false => sample == 1,
+            };
+            line.push(if earth { Cell::Earth } else { Cell::Sea });
+        }
+
+        lines.push(line);
+    }
+
+    Ok(Map::from(lines))
+}
+
+/// A position in the bytes of an image
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    /// Number of bytes left to read
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// Skip whitespace, and `#` comments up to the end of their line when `comments` is set
+    fn skip_blanks(&mut self, comments: bool) {
+        while let Some(b) = self.peek() {
+            match b {
+                b'#' if comments => while self.byte().is_some_and(|b| b != b'\n') {},
+                _ if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn decimal(&mut self) -> Option<u32> {
+        let start = self.pos;
+        let mut value: u32 = 0;
+
+        while let Some(digit) = self.peek().filter(u8::is_ascii_digit) {
+            value = value.checked_mul(10)?.checked_add((digit - b'0') as u32)?;
+            self.pos += 1;
+        }
+
+        (self.pos > start).then_some(value)
+    }
+
+    /// A header field, comments allowed before it
+    fn number(&mut self, field: &'static str) -> Result<u32, NetpbmError> {
+        self.skip_blanks(true);
+
+        match self.peek() {
+            None => Err(NetpbmError::Truncated),
+            Some(_) => self.decimal().ok_or(NetpbmError::Header(field)),
+        }
+    }
+
+    fn invalid_sample(&self) -> NetpbmError {
+        match self.peek() {
+            None => NetpbmError::Truncated,
+            Some(_) => NetpbmError::InvalidByte(self.pos),
+        }
+    }
+
+    /// `P1` pixel: a single `'0'` or `'1'`, whitespace being optional
+    fn plain_bit(&mut self) -> Result<u32, NetpbmError> {
+        self.skip_blanks(false);
+
+        match self.peek() {
+            Some(b @ (b'0' | b'1')) => {
+                self.pos += 1;
+                Ok((b - b'0') as u32)
+            }
+            _ => Err(self.invalid_sample()),
+        }
+    }
+
+    /// `P2` pixel: a decimal number
+    fn plain_sample(&mut self) -> Result<u32, NetpbmError> {
+        self.skip_blanks(false);
+
+        self.decimal().ok_or_else(|| self.invalid_sample())
+    }
+
+    /// `P4` pixel: one bit, most significant first, lines start on a new byte
+    fn raw_bit(&mut self, x: usize, width: usize) -> Result<u32, NetpbmError> {
+        let byte = *self.bytes.get(self.pos).ok_or(NetpbmError::Truncated)?;
+        if x % 8 == 7 || x + 1 == width {
+            self.pos += 1;
+        }
+
+        Ok(((byte >> (7 - x % 8)) & 1) as u32)
+    }
+
+    /// `P5` pixel: one byte, or two big-endian bytes when `maxval` exceeds 255
+    fn raw_sample(&mut self, maxval: u32) -> Result<u32, NetpbmError> {
+        match maxval {
+            0..=255 => self.byte().map(u32::from),
+            _ => self
+                .take(2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as u32),
+        }
+        .ok_or(NetpbmError::Truncated)
+    }
+}
+
+/*
+ * Writing
+ */
+
+/// Colours of sea and unlabelled earth in [`write_ppm`]
+const SEA_COLOUR: [u8; 3] = [255, 255, 255];
+const EARTH_COLOUR: [u8; 3] = [0, 0, 0];
+
+/// Odd multiplier scrambling the island colours
+const COLOUR_SCRAMBLER: u32 = 0x37_79B1;
+
+/// Value scrambled into a black island, skipped: `-1 / COLOUR_SCRAMBLER` modulo `2^24`
+const BLACK_VALUE: u32 = {
+    // Newton's iteration, each step doubles the number of correct low bits
+    let mut inverse = COLOUR_SCRAMBLER;
+    let mut step = 0;
+    while step < 5 {
+        inverse = inverse.wrapping_mul(2u32.wrapping_sub(COLOUR_SCRAMBLER.wrapping_mul(inverse)));
+        step += 1;
+    }
+    inverse.wrapping_neg() & 0xFF_FFFF
+};
+
+/// # Colour of an island in [`write_ppm`]
+///
+/// Colours are scrambled so neighbouring labels look different. The first `0xFFFFFE` labels get
+/// distinct colours, none of them white like the sea nor black like unlabelled earth.
+pub fn island_colour(label: Label) -> [u8; 3] {
+    let mut value = (label % 0xFF_FFFE + 1) as u32;
+    if value >= BLACK_VALUE {
+        value += 1;
+    }
+    let scrambled = value.wrapping_mul(COLOUR_SCRAMBLER) & 0xFF_FFFF;
+    let [_, r, g, b] = (0xFF_FFFF - scrambled).to_be_bytes();
+
+    [r, g, b]
+}
+
+/// Write `map` as a `P1`/`P4` bitmap, earth being black
+pub fn write_pbm(map: &Map, encoding: NetpbmEncoding) -> Vec<u8> {
+    let (width, height) = (map.width(), map.height());
+    let bit = |x, y| match map.get((x, y)) {
+        Some(Cell::Sea) | None => 0,
+        _ => 1,
+    };
+
+    match encoding {
+        NetpbmEncoding::Plain => {
+            let samples = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+            let samples = samples.map(|(x, y)| bit(x, y).to_string());
+            plain(format!("P1\n{} {}\n", width, height), samples)
+        }
+        NetpbmEncoding::Raw => {
+            let mut bytes = format!("P4\n{} {}\n", width, height).into_bytes();
+            for y in 0..height {
+                for chunk_x in (0..width).step_by(8) {
+                    let byte = (chunk_x..width.min(chunk_x + 8))
+                        .fold(0u8, |byte, x| byte | bit(x, y) << (7 - x % 8));
+                    bytes.push(byte);
+                }
+            }
+            bytes
+        }
+    }
+}
+
+/// Write `map` as a `P3`/`P6` pixmap, each island with its [`island_colour`] on a white sea
+pub fn write_ppm(map: &Map, encoding: NetpbmEncoding) -> Vec<u8> {
+    let (width, height) = (map.width(), map.height());
+    let header = |magic| format!("{}\n{} {}\n255\n", magic, width, height);
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|coord| match map.get(coord) {
+            Some(Cell::MarkedEarth(label)) => island_colour(label),
+            Some(Cell::Earth) => EARTH_COLOUR,
+            _ => SEA_COLOUR,
+        });
+
+    match encoding {
+        NetpbmEncoding::Plain => plain(
+            header("P3"),
+            pixels.map(|[r, g, b]| format!("{} {} {}", r, g, b)),
+        ),
+        NetpbmEncoding::Raw => {
+            let mut bytes = header("P6").into_bytes();
+            bytes.extend(pixels.flatten());
+            bytes
+        }
+    }
+}
+
+/// Plain raster: space separated samples, lines kept under 70 characters as the spec asks
+fn plain(header: String, samples: impl Iterator<Item = String>) -> Vec<u8> {
+    let mut text = header;
+    let mut line_len = 0;
+
+    for sample in samples {
+        if line_len > 0 && line_len + 1 + sample.len() > 70 {
+            text.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            text.push(' ');
+            line_len += 1;
+        }
+        line_len += sample.len();
+        text += &sample;
+    }
+
+    text.push('\n');
+    text.into_bytes()
+}
+
+/*
+ * NetpbmError
+ */
+
+/// # Error of [`read_netpbm`]
+///
+/// - `Magic`: not a `P1`, `P2`, `P4` or `P5` file
+/// - `Header`: the named header field is missing or invalid
+/// - `Empty`: the image has no pixel
+/// - `Truncated`: the file ends before the last pixel
+/// - `InvalidByte`: a byte of a plain raster is not a sample, `usize` is its offset in the file
+/// - `Sample`: a greymap sample exceeds the maximum value at line:col
+#[derive(PartialEq)]
+pub enum NetpbmError {
+    Magic,
+    Header(&'static str),
+    Empty,
+    Truncated,
+    InvalidByte(usize),
+    Sample { line: usize, col: usize },
+}
+
+impl fmt::Display for NetpbmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetpbmError::Magic => write!(f, "not a PBM or PGM image"),
+            NetpbmError::Header(field) => write!(f, "invalid image header field '{}'", field),
+            NetpbmError::Empty => write!(f, "image is empty"),
+            NetpbmError::Truncated => write!(f, "image is truncated"),
+            NetpbmError::InvalidByte(offset) => {
+                write!(f, "invalid sample found at byte {}", offset)
+            }
+            NetpbmError::Sample { line, col } => write!(
+                f,
+                "sample exceeding the maximum value found at position {}:{}",
+                line, col
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for NetpbmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for NetpbmError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW_MAP: &str = "\
+#  #######
+ ##      #
+   #######
+";
+
+    fn decode(bytes: &[u8]) -> String {
+        read_netpbm(bytes, None).unwrap().to_string()
+    }
+
+    mod read {
+        use super::*;
+
+        #[test]
+        fn plain_bitmap() {
+            let bytes = b"P1\n# a comment\n3 2\n1 0 0\n011\n";
+            assert_eq!(decode(bytes), "#  \n ##\n");
+        }
+
+        #[test]
+        fn raw_bitmap() {
+            let mut bytes = b"P4 10 2\n".to_vec();
+            bytes.extend([0b1001_1111, 0b1100_0000, 0b0110_0000, 0b0100_0000]);
+
+            assert_eq!(decode(&bytes), "#  #######\n ##      #\n");
+        }
+
+        #[test]
+        fn plain_greymap() {
+            let bytes = b"P2 3 2 # maxval next\n 255\n0 128 127\n255 10 200\n";
+            assert_eq!(decode(bytes), " # \n# #\n");
+            assert_eq!(
+                read_netpbm(bytes, Some(11)).unwrap().to_string(),
+                " ##\n# #\n"
+            );
+        }
+
+        #[test]
+        fn raw_greymap() {
+            let mut bytes = b"P5\n2 1\n255\n".to_vec();
+            bytes.extend([200, 3]);
+            assert_eq!(decode(&bytes), "# \n");
+
+            let mut bytes = b"P5\n2 1\n1000\n".to_vec();
+            bytes.extend([0x01, 0xF5, 0x01, 0xF4]);
+            assert_eq!(decode(&bytes), "# \n");
+        }
+
+        #[test]
+        fn earth_is_black_on_bitmaps_and_bright_on_greymaps() {
+            // the same picture: a dark pixel, then a light one
+            assert_eq!(decode(b"P1 2 1 1 0"), "# \n");
+            assert_eq!(decode(b"P2 2 1 255 0 255"), " #\n");
+        }
+
+        #[test]
+        fn errors() {
+            let error = |bytes: &[u8]| read_netpbm(bytes, None).unwrap_err();
+
+            assert_eq!(error(b"P3 1 1 255 0 0 0"), NetpbmError::Magic);
+            assert_eq!(error(b"P1 x 1"), NetpbmError::Header("width"));
+            assert_eq!(error(b"P2 1 1 0 0"), NetpbmError::Header("maxval"));
+            assert_eq!(error(b"P1 0 1"), NetpbmError::Empty);
+            assert_eq!(error(b"P1 2 2 1 0 1"), NetpbmError::Truncated);
+            assert_eq!(
+                error(b"P5 4294967295 4294967295 255\n\0"),
+                NetpbmError::Truncated
+            );
+            assert_eq!(error(b"P4 65536 65536\n\0"), NetpbmError::Truncated);
+            assert_eq!(error(b"P5 2 1 1000\n\0\0\0"), NetpbmError::Truncated);
+            assert_eq!(error(b"P4 9 1\n\xFF"), NetpbmError::Truncated);
+            assert_eq!(error(b"P1 2 1 1 2"), NetpbmError::InvalidByte(9));
+            assert_eq!(
+                error(b"P2 2 1 15 3 16"),
+                NetpbmError::Sample { line: 1, col: 2 }
+            );
+        }
+    }
+
+    mod write {
+        use super::*;
+
+        #[test]
+        fn bitmap_round_trip() {
+            let map: Map = RAW_MAP.parse().unwrap();
+
+            for encoding in [NetpbmEncoding::Plain, NetpbmEncoding::Raw] {
+                assert_eq!(decode(&write_pbm(&map, encoding)), RAW_MAP);
+            }
+        }
+
+        #[test]
+        fn plain_bitmap() {
+            let map: Map = "# \n #\n".parse().unwrap();
+            assert_eq!(
+                write_pbm(&map, NetpbmEncoding::Plain),
+                b"P1\n2 2\n1 0 0 1\n"
+            );
+        }
+
+        #[test]
+        fn plain_lines_are_short() {
+            let map: Map = RAW_MAP.parse().unwrap();
+            let bytes = write_ppm(&map, NetpbmEncoding::Plain);
+            let text = String::from_utf8(bytes).unwrap();
+
+            assert!(text.starts_with("P3\n10 3\n255\n0 0 0 255 255 255 255 255 255 0 0 0"));
+            assert!(text.lines().all(|line| line.len() <= 70));
+        }
+
+        #[test]
+        fn raw_pixmap() {
+            let map: Map = "0 #\n".parse().unwrap();
+            let bytes = write_ppm(&map, NetpbmEncoding::Raw);
+
+            let mut expected = b"P6\n3 1\n255\n".to_vec();
+            expected.extend(island_colour(0));
+            expected.extend(SEA_COLOUR);
+            expected.extend(EARTH_COLOUR);
+            assert_eq!(bytes, expected);
+        }
+
+        #[test]
+        fn distinct_colours() {
+            let mut colours: Vec<[u8; 3]> = (0..100_000).map(island_colour).collect();
+            colours.sort();
+            colours.dedup();
+
+            assert_eq!(colours.len(), 100_000);
+            assert!(!colours.contains(&SEA_COLOUR));
+        }
+
+        #[test]
+        fn no_black_island() {
+            assert_eq!(
+                BLACK_VALUE.wrapping_mul(COLOUR_SCRAMBLER) & 0xFF_FFFF,
+                0xFF_FFFF
+            );
+            assert!((0..0xFF_FFFE).all(|label| island_colour(label) != EARTH_COLOUR));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::cell::Cell;
+use super::connectivity::Connectivity;
+use super::label::Label;
+use super::labeler::Labeler;
+use super::map::Map;
+use std::collections::{HashMap, HashSet};
+
+/// # Labeler keeping the labels already present on the map
+///
+/// Islands are found by the wrapped [`Labeler`] as if no cell was marked, then each island takes
+/// back one of the labels found on its cells:
+///
+/// - the label used by most of its cells, the smallest one on equality
+/// - a label is kept by a single island, islands are served in the reading order of their first
+///   cell and fall back on their next most used label
+/// - islands left without a label get the next free labels, numbered after the highest kept label
+///
+/// Every cell of an island ends with the same label.
+pub struct PreserveMarks<'a>(pub &'a dyn Labeler);
+
+impl Labeler for PreserveMarks<'_> {
+    fn label(&self, mut map: Map, connectivity: &Connectivity) -> Map {
+        let PreserveMarks(labeler) = self;
+        let mut marks: Vec<((usize, usize), Label)> = vec![];
+
+        for cursor in map.cursor().iter() {
+            if let Cell::MarkedEarth(label) = cursor.read() {
+                marks.push((cursor.coord(), label));
+            }
+        }
+        map.unmark();
+
+        let mut map = labeler.label(map, connectivity);
+        let island_of = |coord| match map.get(coord) {
+            Some(Cell::MarkedEarth(island)) => island,
+            _ => unreachable!("[FATAL] unlabelled earth at {:?}", coord),
+        };
+
+        let island_count = map
+            .cursor()
+            .iter()
+            .filter_map(|c| match c.read() {
+                Cell::MarkedEarth(island) => Some(island + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut votes: Vec<HashMap<Label, usize>> = vec![HashMap::new(); island_count];
+        for &(coord, label) in marks.iter() {
+            *votes[island_of(coord)].entry(label).or_default() += 1;
+        }
+
+        let mut kept_labels: HashSet<Label> = HashSet::new();
+        let mut labels: Vec<Option<Label>> = votes
+            .into_iter()
+            .map(|votes| {
+                let mut candidates: Vec<(Label, usize)> = votes.into_iter().collect();
+                candidates.sort_by_key(|&(label, count)| (usize::MAX - count, label));
+
+                let (label, _) = candidates
+                    .into_iter()
+                    .find(|(label, _)| !kept_labels.contains(label))?;
+                kept_labels.insert(label);
+                Some(label)
+            })
+            .collect();
+
+        let next_labels = kept_labels.iter().max().map_or(0, |label| label + 1)..;
+        let unlabelled = labels.iter_mut().filter(|label| label.is_none());
+        for (next_label, label) in next_labels.zip(unlabelled) {
+            *label = Some(next_label);
+        }
+
+        map.relabel(|island| labels[island].expect("[FATAL] island without label"));
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::labeler::{FloodFill, UnionFind};
+
+    fn label(raw_map: &str) -> String {
+        let map: Map = raw_map.parse().unwrap();
+        PreserveMarks(&FloodFill)
+            .label(map, &Connectivity::four())
+            .to_string()
+    }
+
+    #[test]
+    fn keep_marks() {
+        assert_eq!(label("5 # 2\n"), "5 6 2\n");
+    }
+
+    #[test]
+    fn unmarked_map() {
+        assert_eq!(label("# # #\n"), "0 1 2\n");
+    }
+
+    #[test]
+    fn partially_marked_island() {
+        assert_eq!(label("#3#\n# #\n 1 \n"), "333\n3 3\n 1 \n");
+    }
+
+    #[test]
+    fn most_used_label_wins() {
+        assert_eq!(label("4477\n   7\n"), "7777\n   7\n");
+        assert_eq!(label("7744\n"), "4444\n");
+    }
+
+    #[test]
+    fn label_reused_by_two_islands() {
+        assert_eq!(label("22 2 #\n"), "22 3 4\n");
+    }
+
+    #[test]
+    fn any_labeler() {
+        let raw_map = "1## 9\n  # #\n#8  #\n";
+        let map: Map = raw_map.parse().unwrap();
+        let union_find = PreserveMarks(&UnionFind).label(map, &Connectivity::four());
+
+        assert_eq!(union_find.to_string(), label(raw_map));
+    }
+}
@@ -0,0 +1,253 @@
+use super::connectivity::Connectivity;
+use super::filler::Filler;
+use super::grid::Grid;
+use super::label::Label;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// # Regions of a single class of cells
+///
+/// - `class`: the value of the cells
+/// - `regions`: number of connected regions of the class
+/// - `area`: number of cells of the class
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassRegions<T> {
+    pub class: T,
+    pub regions: usize,
+    pub area: usize,
+}
+
+/// # Connected regions of every class of a grid
+///
+/// Neighbouring cells of equal value (their class) are connected into regions, for every class
+/// at once: a terrain of forest, desert and water gives the forests, the deserts and the lakes.
+/// Regions are numbered from `0` within their class, in the reading order of their first cell.
+///
+/// ```
+/// # use garm_playground::libs::island::{Connectivity, ParseOptions, Regions};
+/// let (grid, _) = ParseOptions::new().parse_classes("TT~\n~T~\n~~T\n").unwrap();
+/// let regions = Regions::partial(grid, &Connectivity::four());
+///
+/// assert_eq!(regions.count(&'T'), 2);
+/// assert_eq!(regions.count(&'~'), 2);
+/// assert_eq!(regions.region_map(&'T').get((2, 2)), Some(Some(1)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regions<T> {
+    grid: Grid<Option<T>>,
+    labels: Grid<Option<Label>>,
+    classes: Vec<ClassRegions<T>>,
+}
+
+impl<T: Copy + Ord> Regions<T> {
+    /// Regions of a grid where every cell has a class
+    pub fn new(grid: Grid<T>, connectivity: &Connectivity) -> Self {
+        let cells = grid.cells().iter().map(|&class| Some(class)).collect();
+        let grid = Grid::new(grid.width(), grid.height(), cells).expect("[FATAL] same size");
+
+        Regions::partial(grid, connectivity)
+    }
+
+    /// Regions of a grid where `None` cells, e.g. the padding of short lines, belong to no class
+    pub fn partial(grid: Grid<Option<T>>, connectivity: &Connectivity) -> Self {
+        let same_class = |a: &Option<T>, b: &Option<T>| a.is_some() && a == b;
+        let mut filler = Filler::new(&grid, connectivity.clone(), same_class);
+        // class of every region and its label in the class, regions being filled in reading order
+        let mut region_labels: Vec<Label> = vec![];
+        let mut classes: BTreeMap<T, ClassRegions<T>> = BTreeMap::new();
+
+        for cursor in grid.cursor().iter() {
+            let Some(value) = cursor.read() else {
+                continue;
+            };
+            let class = classes.entry(value).or_insert(ClassRegions {
+                class: value,
+                regions: 0,
+                area: 0,
+            });
+
+            class.area += 1;
+            if filler.fill(cursor.coord()).is_some() {
+                region_labels.push(class.regions);
+                class.regions += 1;
+            }
+        }
+
+        let mut labels = filler.into_labels();
+        for label in labels.cells_mut().iter_mut().flatten() {
+            *label = region_labels[*label];
+        }
+
+        Regions {
+            grid,
+            labels,
+            classes: classes.into_values().collect(),
+        }
+    }
+
+    /// Every class found, sorted by value
+    pub fn classes(&self) -> &[ClassRegions<T>] {
+        &self.classes
+    }
+
+    /// Number of regions of `class`, `0` when the grid has no such cell
+    pub fn count(&self, class: &T) -> usize {
+        self.class(class).map_or(0, |class| class.regions)
+    }
+
+    pub fn class(&self, class: &T) -> Option<&ClassRegions<T>> {
+        let i = self.classes.binary_search_by(|c| c.class.cmp(class)).ok()?;
+        Some(&self.classes[i])
+    }
+
+    /// The labelled grid, `None` for cells without a class
+    pub fn grid(&self) -> &Grid<Option<T>> {
+        &self.grid
+    }
+
+    /// Label of every cell among the regions of its class, `None` for cells without a class
+    pub fn labels(&self) -> &Grid<Option<Label>> {
+        &self.labels
+    }
+
+    /// Label of the cells of `class`, `None` for the other cells
+    pub fn region_map(&self, class: &T) -> Grid<Option<Label>> {
+        let mut region_map = Grid::filled(self.grid.width(), self.grid.height(), None);
+        let cells = self.grid.cells().iter().zip(self.labels.cells());
+
+        for (region, (cell, &label)) in region_map.cells_mut().iter_mut().zip(cells) {
+            if cell.as_ref() == Some(class) {
+                *region = label;
+            }
+        }
+
+        region_map
+    }
+}
+
+/// Table of the classes, with their number of regions and cells
+impl<T: fmt::Debug> fmt::Display for Regions<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>8} {:>8} {:>8}", "class", "regions", "area")?;
+
+        for class in &self.classes {
+            let name = format!("{:?}", class.class);
+            writeln!(f, "{:>8} {:>8} {:>8}", name, class.regions, class.area)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::map::ParseOptions;
+
+    const TERRAIN: &str = "\
+ff..~~
+ff.~~f
+..~~ff
+~~~.ff
+";
+
+    fn regions(raw: &str, connectivity: Connectivity) -> Regions<char> {
+        let (grid, _) = ParseOptions::new().parse_classes(raw).unwrap();
+        Regions::partial(grid, &connectivity)
+    }
+
+    #[test]
+    fn every_class() {
+        let regions = regions(TERRAIN, Connectivity::four());
+        let summary: Vec<(char, usize, usize)> = regions
+            .classes()
+            .iter()
+            .map(|c| (c.class, c.regions, c.area))
+            .collect();
+
+        assert_eq!(summary, [('.', 3, 6), ('f', 2, 9), ('~', 1, 9)]);
+        assert_eq!(regions.count(&'f'), 2);
+        assert_eq!(regions.count(&'#'), 0);
+        assert_eq!(regions.class(&'#'), None);
+    }
+
+    #[test]
+    fn labels_in_reading_order() {
+        let regions = regions(TERRAIN, Connectivity::four());
+        let labels: Vec<Label> = regions.labels().cells().iter().flatten().copied().collect();
+
+        #[rustfmt::skip]
+        assert_eq!(labels, [
+            0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 1,
+            1, 1, 0, 0, 1, 1,
+            0, 0, 0, 2, 1, 1,
+        ]);
+    }
+
+    #[test]
+    fn region_map() {
+        let regions = regions(TERRAIN, Connectivity::four());
+        let map: String = regions
+            .region_map(&'.')
+            .cells()
+            .iter()
+            .map(|label| label.map_or(' ', |label| char::from(b'0' + label as u8)))
+            .collect();
+
+        assert_eq!(map, "  00    0   11       2  ");
+    }
+
+    #[test]
+    fn connectivity() {
+        let regions = regions(TERRAIN, Connectivity::eight());
+
+        assert_eq!(regions.count(&'.'), 2);
+        assert_eq!(regions.count(&'f'), 2);
+    }
+
+    #[test]
+    fn islands_and_sea() {
+        let regions = regions("# #\n   \n# #\n", Connectivity::four());
+
+        assert_eq!(regions.count(&'#'), 4);
+        assert_eq!(regions.count(&' '), 1);
+    }
+
+    #[test]
+    fn other_cell_types() {
+        let grid = Grid::new(3, 2, vec![3u8, 7, 3, 7, 3, 7]).unwrap();
+        let regions = Regions::new(grid, &Connectivity::four());
+
+        assert_eq!(regions.count(&3), 3);
+        assert_eq!(regions.count(&7), 3);
+    }
+
+    #[test]
+    fn ragged_lines() {
+        let regions = regions("TT\nT\n", Connectivity::four());
+        let summary: Vec<(char, usize, usize)> = regions
+            .classes()
+            .iter()
+            .map(|c| (c.class, c.regions, c.area))
+            .collect();
+
+        // the padding of the short line is in no class
+        assert_eq!(summary, [('T', 1, 3)]);
+        assert_eq!(regions.count(&' '), 0);
+        assert_eq!(regions.labels().cells(), [Some(0), Some(0), Some(0), None]);
+        assert_eq!(regions.grid().get((1, 1)), Some(None));
+    }
+
+    #[test]
+    fn display() {
+        let regions = regions("ab\nba\n", Connectivity::four());
+
+        let table = [
+            "   class  regions     area",
+            "     'a'        2        2",
+            "     'b'        2        2",
+        ];
+        assert_eq!(regions.to_string(), table.join("\n") + "\n");
+    }
+}
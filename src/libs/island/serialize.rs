@@ -0,0 +1,212 @@
+use super::cell::{Cell, EARTH_CHAR, SEA_CHAR};
+use super::format::MapFormat;
+use super::label::{alphabet, Alphabet, ALPHABET_NAMES};
+use super::map::Map;
+use serde::ser::SerializeStruct;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Characters of the serialised rows: the default format with [`Alphabet::UNICODE`] marks,
+/// so maps labelled with less than 36 islands look like their text form
+fn row_format() -> MapFormat {
+    MapFormat::new(SEA_CHAR, EARTH_CHAR, Alphabet::UNICODE).expect("[FATAL] invalid row format")
+}
+
+/// # Serialise a format as its characters and the name of its alphabet
+///
+/// `{ "sea": " ", "earth": "#", "marks": "base36" }`, see [`ALPHABET_NAMES`]
+impl Serialize for MapFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let marks = ALPHABET_NAMES
+            .into_iter()
+            .find(|&name| alphabet(name) == Some(self.marks()))
+            .ok_or_else(|| ser::Error::custom("alphabet without a name cannot be serialised"))?;
+
+        let mut format = serializer.serialize_struct("MapFormat", 3)?;
+        format.serialize_field("sea", &self.sea())?;
+        format.serialize_field("earth", &self.earth())?;
+        format.serialize_field("marks", marks)?;
+        format.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "MapFormat")]
+struct RawFormat {
+    sea: char,
+    earth: char,
+    marks: String,
+}
+
+impl<'de> Deserialize<'de> for MapFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawFormat { sea, earth, marks } = RawFormat::deserialize(deserializer)?;
+        let marks = alphabet(&marks)
+            .ok_or_else(|| de::Error::custom(format!("unknown alphabet '{}'", marks)))?;
+
+        MapFormat::new(sea, earth, marks).map_err(de::Error::custom)
+    }
+}
+
+/// # Serialise a map as its format and a sequence of rows of characters
+///
+/// `{ "format": { ... }, "rows": ["0 #", " 1 "] }`, the rows being written with the row format
+/// whatever the format of the map. Labels too large for [`Alphabet::UNICODE`] cannot be
+/// serialised
+impl Serialize for Map {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let format = row_format();
+        let rows: Vec<String> = (0..self.height())
+            .map(|y| {
+                (0..self.width())
+                    .map(|x| match self.get((x, y)) {
+                        Some(Cell::MarkedEarth(label)) if format.marks().char(label).is_none() => {
+                            Err(ser::Error::custom(format!(
+                                "label {} is too large to be serialised",
+                                label
+                            )))
+                        }
+                        cell => Ok(format.char(cell.unwrap_or(Cell::Sea))),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, S::Error>>()?;
+
+        let mut map = serializer.serialize_struct("Map", 2)?;
+        map.serialize_field("format", self.format())?;
+        map.serialize_field("rows", &rows)?;
+        map.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Map")]
+struct RawMap {
+    format: MapFormat,
+    rows: Vec<String>,
+}
+
+/// # Deserialise a map from its format and a sequence of rows of characters
+///
+/// Short rows are padded with sea
+impl<'de> Deserialize<'de> for Map {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let format = row_format();
+        let RawMap {
+            format: map_format,
+            rows,
+        } = RawMap::deserialize(deserializer)?;
+
+        let lines = rows
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        format.cell(c).map_err(|_| {
+                            de::Error::custom(format!(
+                                "invalid character '{}' found at position {}:{}",
+                                c,
+                                y + 1,
+                                x + 1
+                            ))
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, D::Error>>()?;
+
+        Ok(Map::with_format(lines, map_format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::connectivity::Connectivity;
+    use crate::libs::island::labeler::{FloodFill, Labeler};
+    use crate::libs::island::map::ParseOptions;
+
+    /// JSON of a map of the default format
+    fn rows(rows: &str) -> String {
+        format!(
+            r##"{{"format":{{"sea":" ","earth":"#","marks":"base36"}},"rows":{}}}"##,
+            rows
+        )
+    }
+
+    #[test]
+    fn rows_of_strings() {
+        let map: Map = "0 #\n 1 \n".parse().unwrap();
+        assert_eq!(
+            serde_json::to_string(&map).unwrap(),
+            r##"{"format":{"sea":" ","earth":"#","marks":"base36"},"rows":["0 #"," 1 "]}"##
+        );
+    }
+
+    #[test]
+    fn large_labels() {
+        let mut map: Map = "# #\n".parse().unwrap();
+        map.write((0, 0), Cell::MarkedEarth(36));
+        map.write((2, 0), Cell::MarkedEarth(1000));
+
+        let json = serde_json::to_string(&map).unwrap();
+        let map: Map = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(map.get((0, 0)), Some(Cell::MarkedEarth(36)));
+        assert_eq!(map.get((2, 0)), Some(Cell::MarkedEarth(1000)));
+    }
+
+    #[test]
+    fn too_large_label() {
+        let mut map: Map = "#\n".parse().unwrap();
+        map.write((0, 0), Cell::MarkedEarth(Alphabet::UNICODE.len()));
+
+        assert!(serde_json::to_string(&map).is_err());
+    }
+
+    #[test]
+    fn invalid_row() {
+        let result = serde_json::from_str::<Map>(&rows(r##"["# ","#!"]"##));
+        let message = result.unwrap_err().to_string();
+
+        assert!(message.starts_with("invalid character '!' found at position 2:2"));
+    }
+
+    #[test]
+    fn ragged_rows_are_padded() {
+        let map: Map = serde_json::from_str(&rows(r##"["#","# #"]"##)).unwrap();
+        assert_eq!(map.to_string(), "#  \n# #\n");
+    }
+
+    #[test]
+    fn keeps_the_format() {
+        let (map, _) = ParseOptions::new()
+            .format(MapFormat::DOTS)
+            .parse("X.X\n.XX\n")
+            .unwrap();
+        let map = FloodFill.label(map, &Connectivity::four());
+        let round_trip: Map = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+
+        assert_eq!(round_trip.format(), &MapFormat::DOTS);
+        assert_eq!(round_trip, map);
+        assert_eq!(round_trip.to_string(), "0.1\n.11\n");
+
+        let map = Map::with_format(vec![vec![Cell::Earth, Cell::Sea]], MapFormat::BINARY);
+        let round_trip: Map = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+
+        assert_eq!(round_trip, map);
+        assert_eq!(round_trip.to_string(), "10\n");
+    }
+
+    #[test]
+    fn invalid_format() {
+        let json = r##"{"format":{"sea":"#","earth":"#","marks":"none"},"rows":[]}"##;
+        let message = serde_json::from_str::<Map>(json).unwrap_err().to_string();
+        assert!(message.starts_with("character '#' is used for several kinds of cells"));
+
+        let json = r##"{"format":{"sea":" ","earth":"#","marks":"klingon"},"rows":[]}"##;
+        let message = serde_json::from_str::<Map>(json).unwrap_err().to_string();
+        assert!(message.starts_with("unknown alphabet 'klingon'"));
+    }
+}
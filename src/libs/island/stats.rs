@@ -0,0 +1,240 @@
+use super::cell::Cell;
+use super::connectivity::Connectivity;
+use super::cursor::DIRECTIONS;
+use super::label::Label;
+use super::lakes::Lakes;
+use super::map::Map;
+use std::fmt;
+
+/// Inclusive `(x, y)` corners of the smallest rectangle holding an island
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
+
+impl BoundingBox {
+    fn new(coord: (usize, usize)) -> Self {
+        BoundingBox {
+            min: coord,
+            max: coord,
+        }
+    }
+
+    fn extend(&mut self, (x, y): (usize, usize)) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    pub fn width(&self) -> usize {
+        self.max.0 - self.min.0 + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.max.1 - self.min.1 + 1
+    }
+}
+
+/// # Statistics of a single island
+///
+/// - `area`: number of cells
+/// - `perimeter`: number of cell edges between the island and the sea (the outside of the map is sea)
+/// - `centroid`: mean `(x, y)` coordinate of the island cells
+/// - `touches_border`: at least one cell has a neighbour outside of the map
+/// - `holes`: number of [lakes](super::lakes::Lake) enclosed by the island
+#[derive(Debug, Clone, PartialEq)]
+pub struct IslandStats {
+    pub label: Label,
+    pub area: usize,
+    pub bounding_box: BoundingBox,
+    pub perimeter: usize,
+    pub centroid: (f64, f64),
+    pub touches_border: bool,
+    pub holes: usize,
+}
+
+impl IslandStats {
+    fn new(label: Label, coord: (usize, usize)) -> Self {
+        IslandStats {
+            label,
+            area: 0,
+            bounding_box: BoundingBox::new(coord),
+            perimeter: 0,
+            centroid: (0.0, 0.0),
+            touches_border: false,
+            holes: 0,
+        }
+    }
+}
+
+/// # List of [`IslandStats`] ordered by label
+///
+/// displayed as a table with one line per island
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats(pub Vec<IslandStats>);
+
+impl Stats {
+    /// Compute the statistics of every island of an already labelled map
+    ///
+    /// `connectivity` is the one used to label the map, it defines which sea cells form lakes
+    pub fn new(map: &Map, connectivity: &Connectivity) -> Self {
+        let cursor = map.cursor();
+        let mut islands: Vec<Option<IslandStats>> = vec![];
+        let mut coord_sums: Vec<(usize, usize)> = vec![];
+
+        for cursor in cursor.iter() {
+            let label = match cursor.read() {
+                Cell::MarkedEarth(label) => label,
+                _ => continue,
+            };
+            let (x, y) = cursor.coord();
+
+            if islands.len() <= label {
+                islands.resize(label + 1, None);
+                coord_sums.resize(label + 1, (0, 0));
+            }
+            let island = islands[label].get_or_insert_with(|| IslandStats::new(label, (x, y)));
+
+            island.area += 1;
+            island.bounding_box.extend((x, y));
+            coord_sums[label].0 += x;
+            coord_sums[label].1 += y;
+
+            let neighbours = DIRECTIONS.map(|dir| cursor.move_dir(dir).map(|c| c.read()));
+            island.perimeter += neighbours
+                .iter()
+                .filter(|cell| !matches!(cell, Some(Cell::MarkedEarth(_))))
+                .count();
+            island.touches_border |= neighbours.iter().any(|cell| cell.is_none());
+        }
+
+        let lakes = Lakes::new(map, connectivity);
+        let islands = islands
+            .into_iter()
+            .zip(coord_sums)
+            .filter_map(|(island, (sum_x, sum_y))| {
+                let mut island = island?;
+                let area = island.area as f64;
+                island.centroid = (sum_x as f64 / area, sum_y as f64 / area);
+                island.holes = lakes.holes(island.label);
+                Some(island)
+            })
+            .collect();
+
+        Stats(islands)
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Stats(islands) = self;
+
+        writeln!(
+            f,
+            "{:>8} {:>8} {:>21} {:>9} {:>19} {:>6} {:>6}",
+            "label", "area", "bounding box", "perimeter", "centroid", "border", "holes"
+        )?;
+
+        for island in islands {
+            let BoundingBox { min, max } = island.bounding_box;
+            let bounding_box = format!("({},{})-({},{})", min.0, min.1, max.0, max.1);
+            let centroid = format!("({:.2},{:.2})", island.centroid.0, island.centroid.1);
+            let border = if island.touches_border { "yes" } else { "no" };
+
+            writeln!(
+                f,
+                "{:>8} {:>8} {:>21} {:>9} {:>19} {:>6} {:>6}",
+                island.label,
+                island.area,
+                bounding_box,
+                island.perimeter,
+                centroid,
+                border,
+                island.holes
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELLED_MAP: &str = "\
+00
+0  1
+  11
+";
+
+    #[test]
+    fn one_record_per_island() {
+        let map: Map = LABELLED_MAP.parse().unwrap();
+        let Stats(islands) = Stats::new(&map, &Connectivity::four());
+
+        assert_eq!(
+            islands,
+            vec![
+                IslandStats {
+                    label: 0,
+                    area: 3,
+                    bounding_box: BoundingBox {
+                        min: (0, 0),
+                        max: (1, 1)
+                    },
+                    perimeter: 8,
+                    centroid: (1.0 / 3.0, 1.0 / 3.0),
+                    touches_border: true,
+                    holes: 0,
+                },
+                IslandStats {
+                    label: 1,
+                    area: 3,
+                    bounding_box: BoundingBox {
+                        min: (2, 1),
+                        max: (3, 2)
+                    },
+                    perimeter: 8,
+                    centroid: (8.0 / 3.0, 5.0 / 3.0),
+                    touches_border: true,
+                    holes: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn inner_island() {
+        let map: Map = "00000\n0   0\n0 1 0\n0   0\n00000\n".parse().unwrap();
+        let Stats(islands) = Stats::new(&map, &Connectivity::four());
+
+        assert_eq!(islands[0].perimeter, 32);
+        assert!(islands[0].touches_border);
+        assert_eq!(islands[0].holes, 1);
+
+        assert_eq!(islands[1].area, 1);
+        assert_eq!(islands[1].perimeter, 4);
+        assert_eq!(islands[1].centroid, (2.0, 2.0));
+        assert_eq!(islands[1].bounding_box.width(), 1);
+        assert!(!islands[1].touches_border);
+    }
+
+    #[test]
+    fn unlabelled_map() {
+        let map: Map = "## \n".parse().unwrap();
+        assert_eq!(Stats::new(&map, &Connectivity::four()), Stats(vec![]));
+    }
+
+    #[test]
+    fn table() {
+        let map: Map = LABELLED_MAP.parse().unwrap();
+        let table = Stats::new(&map, &Connectivity::four()).to_string();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("perimeter"));
+        assert!(lines[1].contains("(0,0)-(1,1)"));
+        assert!(lines[2].contains("(2.67,1.67)"));
+    }
+}
@@ -0,0 +1,402 @@
+use super::cell::Cell;
+use super::connectivity::Connectivity;
+use super::disjoint_set::DisjointSet;
+use super::error::{Error, ErrorList};
+use super::format::MapFormat;
+use super::label::{Label, LabelEncoder};
+use super::map::{LineParser, ParseOptions, RaggedLines};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// # Row by row labeler for maps that do not fit in memory
+///
+/// The raw map is read twice from any [`BufRead`], one line at a time:
+///
+/// - [`StreamLabeler::scan`] counts the islands, only the provisional labels of the last rows
+///   reached by the connectivity and the table of equivalent labels are kept
+/// - [`StreamLabeler::relabel`] reads the same input again and writes each line with its
+///   final labels
+///
+/// Labels, errors and output characters are the ones of [`run_with`](super::run_with()),
+/// already marked cells are labelled again like unmarked earth. The map is always parsed strictly,
+/// a lenient [`ParseOptions`] being treated like a strict one.
+#[derive(Debug, Clone)]
+pub struct StreamLabeler {
+    connectivity: Connectivity,
+    ragged_lines: RaggedLines,
+    format: MapFormat,
+    sets: DisjointSet,
+    width: usize,
+}
+
+impl StreamLabeler {
+    /// First pass over the raw map, reading stops once the maximum number of errors of
+    /// `options` is reached
+    pub fn scan<R: BufRead>(
+        reader: R,
+        connectivity: &Connectivity,
+        options: &ParseOptions,
+    ) -> Result<Self, StreamError> {
+        let ParseOptions {
+            ragged_lines,
+            format,
+            max_errors,
+            ..
+        } = *options;
+        let mut scanner = RowScanner::new(connectivity);
+        let mut parser = LineParser::new(ragged_lines, format);
+        let mut sets = DisjointSet::new();
+        let mut width = 0;
+        let mut line_count = 0;
+
+        for line in reader.lines() {
+            let cells = parser.parse(&line?);
+            width = width.max(cells.len());
+            line_count += 1;
+
+            scanner.scan(&cells, &mut sets);
+
+            if let Some(max_errors) = max_errors {
+                if parser.errors.len() >= max_errors {
+                    parser.errors.truncate(max_errors);
+                    return Err(StreamError::Map(ErrorList(parser.errors)));
+                }
+            }
+        }
+
+        if line_count == 0 {
+            return Err(StreamError::Map(ErrorList(vec![Error::EmptyMap])));
+        }
+        if !parser.errors.is_empty() {
+            return Err(StreamError::Map(ErrorList(parser.errors)));
+        }
+
+        Ok(StreamLabeler {
+            connectivity: connectivity.clone(),
+            ragged_lines,
+            format,
+            sets,
+            width,
+        })
+    }
+
+    /// Number of islands found by [`StreamLabeler::scan`]
+    pub fn island_count(&self) -> usize {
+        self.sets.set_count()
+    }
+
+    /// Width of the longest line, shorter lines are padded with sea on output
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Second pass over the same raw map, written to `writer` with `encoder`
+    ///
+    /// An input that differs from the scanned one is reported as [`io::ErrorKind::InvalidData`]
+    pub fn relabel<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+        encoder: &dyn LabelEncoder,
+    ) -> Result<(), StreamError> {
+        let mut scanner = RowScanner::new(&self.connectivity);
+        let mut parser = LineParser::new(self.ragged_lines, self.format);
+        let mut sets = self.sets.clone();
+        let mut final_labels: Vec<Option<Label>> = vec![None; sets.len()];
+        let mut next_label = 0;
+
+        for line in reader.lines() {
+            let mut cells = parser.parse(&line?);
+            cells.resize(self.width.max(cells.len()), Cell::Sea);
+
+            if cells.len() > self.width || !parser.errors.is_empty() {
+                return Err(input_changed());
+            }
+
+            let provisional = scanner.scan(&cells, &mut sets);
+            if sets.len() != final_labels.len() {
+                return Err(input_changed());
+            }
+
+            let encoded: Vec<String> = cells
+                .into_iter()
+                .zip(provisional)
+                .map(|(cell, label)| match label {
+                    Some(label) => {
+                        let root = sets.find(label);
+                        let label = *final_labels[root].get_or_insert_with(|| {
+                            next_label += 1;
+                            next_label - 1
+                        });
+                        encoder.encode_cell(Cell::MarkedEarth(label), &self.format)
+                    }
+                    None => encoder.encode_cell(cell, &self.format),
+                })
+                .collect();
+
+            writeln!(writer, "{}", encoded.join(encoder.separator()))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn input_changed() -> StreamError {
+    StreamError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "input changed between the two passes",
+    ))
+}
+
+/// # Provisional labels of the last scanned rows
+///
+/// Works like [`UnionFind`](super::UnionFind), but forgets the rows out of reach of the
+/// connectivity. Scanning the same lines always gives the same provisional labels.
+struct RowScanner {
+    backward_offsets: Vec<(isize, isize)>,
+    rows: VecDeque<Vec<Option<usize>>>,
+    reach: usize,
+    next_label: usize,
+}
+
+impl RowScanner {
+    fn new(connectivity: &Connectivity) -> Self {
+        // connectivity is symmetric: looking backward is enough to see every edge
+        let backward_offsets: Vec<(isize, isize)> = connectivity
+            .offsets()
+            .into_iter()
+            .filter(|&(dx, dy)| dy < 0 || (dy == 0 && dx < 0))
+            .collect();
+        let reach = backward_offsets
+            .iter()
+            .map(|&(_, dy)| dy.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+
+        RowScanner {
+            backward_offsets,
+            rows: VecDeque::with_capacity(reach + 1),
+            reach,
+            next_label: 0,
+        }
+    }
+
+    /// Provisional labels of a new row, sets are created in `sets` when missing
+    fn scan(&mut self, cells: &[Cell], sets: &mut DisjointSet) -> Vec<Option<usize>> {
+        let mut line: Vec<Option<usize>> = vec![None; cells.len()];
+
+        for (x, cell) in cells.iter().enumerate() {
+            if *cell == Cell::Sea {
+                continue;
+            }
+
+            let mut label = None;
+            for &(dx, dy) in self.backward_offsets.iter() {
+                let neighbour = x.checked_add_signed(dx).and_then(|nx| {
+                    let neighbour_line = match dy.unsigned_abs() {
+                        0 => &line,
+                        dy => self.rows.get(self.rows.len().checked_sub(dy)?)?,
+                    };
+                    *neighbour_line.get(nx)?
+                });
+
+                if let Some(neighbour) = neighbour {
+                    label = Some(match label {
+                        Some(label) => sets.union(label, neighbour),
+                        None => neighbour,
+                    });
+                }
+            }
+
+            line[x] = Some(label.unwrap_or_else(|| {
+                let label = self.next_label;
+                self.next_label += 1;
+                if label == sets.len() {
+                    sets.make_set();
+                }
+                label
+            }));
+        }
+
+        if self.reach > 0 {
+            if self.rows.len() == self.reach {
+                self.rows.pop_front();
+            }
+            self.rows.push_back(line.clone());
+        }
+
+        line
+    }
+}
+
+/*
+ * StreamError
+ */
+
+/// # Error of a [`StreamLabeler`]
+///
+/// - `Io`: the input could not be read or the output written
+/// - `Map`: the raw map is invalid, see [`Error`]
+pub enum StreamError {
+    Io(io::Error),
+    Map(ErrorList),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => write!(f, "{}", error),
+            StreamError::Map(errors) => write!(f, "{}", errors),
+        }
+    }
+}
+
+impl fmt::Debug for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+impl From<ErrorList> for StreamError {
+    fn from(errors: ErrorList) -> Self {
+        StreamError::Map(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::label::{Alphabet, Csv};
+    use crate::libs::island::map::Map;
+    use crate::libs::island::{run_with, Options};
+
+    const MAP: &str = "\
+# ## #
+ ## ##
+#   #
+## ###
+#5 # #
+";
+
+    fn stream(raw_map: &str, connectivity: &Connectivity, encoder: &dyn LabelEncoder) -> String {
+        let labeler =
+            StreamLabeler::scan(raw_map.as_bytes(), connectivity, &ParseOptions::new()).unwrap();
+        let mut output = vec![];
+        labeler
+            .relabel(raw_map.as_bytes(), &mut output, encoder)
+            .unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn island_count() {
+        let labeler =
+            StreamLabeler::scan(MAP.as_bytes(), &Connectivity::four(), &ParseOptions::new())
+                .unwrap();
+
+        assert_eq!(labeler.island_count(), 4);
+        assert_eq!(labeler.width(), 6);
+    }
+
+    #[test]
+    fn same_as_run_with() {
+        for connectivity in [
+            Connectivity::four(),
+            Connectivity::eight(),
+            Connectivity::knight(),
+        ] {
+            let options = Options {
+                connectivity: connectivity.clone(),
+                ..Options::default()
+            };
+
+            assert_eq!(
+                stream(MAP, &connectivity, &Alphabet::BASE36),
+                run_with(MAP, &options).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn with_separator() {
+        let options = Options {
+            encoder: Box::new(Csv),
+            ..Options::default()
+        };
+
+        assert_eq!(
+            stream(MAP, &Connectivity::four(), &Csv),
+            run_with(MAP, &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn far_reaching_connectivity() {
+        let connectivity: Connectivity = "0:3".parse().unwrap();
+        let raw_map = "#\n \n \n#\n#\n";
+
+        assert_eq!(
+            stream(raw_map, &connectivity, &Alphabet::BASE36),
+            "0\n \n \n0\n1\n"
+        );
+    }
+
+    #[test]
+    fn map_errors() {
+        let scan = |raw_map: &str| {
+            StreamLabeler::scan(
+                raw_map.as_bytes(),
+                &Connectivity::four(),
+                &ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert_eq!(scan(""), ErrorList(vec![Error::EmptyMap]).to_string());
+        assert_eq!(
+            scan("#!\n\n##\n"),
+            "#!\n\n##\n".parse::<Map>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn fail_fast() {
+        let options = ParseOptions::new().fail_fast();
+        // the second line is never read
+        let raw_map = b"#!\n\xff\n";
+        let result = StreamLabeler::scan(&raw_map[..], &Connectivity::four(), &options);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "#!\n".parse::<Map>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn input_changed_between_passes() {
+        let labeler = StreamLabeler::scan(
+            "# #\n".as_bytes(),
+            &Connectivity::four(),
+            &ParseOptions::new(),
+        )
+        .unwrap();
+        let relabel =
+            |raw_map: &str| labeler.relabel(raw_map.as_bytes(), io::sink(), &Alphabet::BASE36);
+
+        assert!(relabel("# #\n").is_ok());
+        assert!(matches!(relabel("####\n"), Err(StreamError::Io(_))));
+        assert!(matches!(relabel("# #\n   \n#\n"), Err(StreamError::Io(_))));
+    }
+}
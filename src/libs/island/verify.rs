@@ -0,0 +1,171 @@
+use super::cell::Cell;
+use super::connectivity::Connectivity;
+use super::error::{Error, ErrorList};
+use super::label::Label;
+use super::labeler::Labeler;
+use super::map::{Map, MapResult};
+use std::collections::{HashMap, HashSet};
+
+/// # Check the labels of an already labelled map
+///
+/// Islands are found by `labeler` whatever their labels, then the map is walked in reading order:
+///
+/// - an island using a label different from the one of its first cell gives a
+///   [`Error::MixedLabels`], once per extra label
+/// - a label already used by a previous island gives a [`Error::DuplicateLabel`], once per island
+/// - every unlabelled earth cell gives a [`Error::UnlabelledEarth`]
+pub fn verify(mut map: Map, labeler: &dyn Labeler, connectivity: &Connectivity) -> MapResult<()> {
+    let cells: Vec<((usize, usize), Cell)> = map
+        .cursor()
+        .iter()
+        .map(|cursor| (cursor.coord(), cursor.read()))
+        .collect();
+
+    map.unmark();
+    let islands = labeler.label(map, connectivity);
+
+    let mut errors: Vec<Error> = vec![];
+    let mut island_labels: HashMap<Label, Label> = HashMap::new();
+    let mut label_owners: HashMap<Label, Label> = HashMap::new();
+    // `(island, label)` pairs already reported, for each kind of error
+    let mut reported_mixed: HashSet<(Label, Label)> = HashSet::new();
+    let mut reported_duplicates: HashSet<(Label, Label)> = HashSet::new();
+
+    for ((x, y), cell) in cells {
+        let (line, col) = (y + 1, x + 1);
+        let island = match islands.get((x, y)) {
+            Some(Cell::MarkedEarth(island)) => island,
+            _ => continue,
+        };
+        let label = match cell {
+            Cell::MarkedEarth(label) => label,
+            _ => {
+                errors.push(Error::UnlabelledEarth { line, col });
+                continue;
+            }
+        };
+
+        let island_label = *island_labels.entry(island).or_insert(label);
+        if island_label != label && reported_mixed.insert((island, label)) {
+            errors.push(Error::MixedLabels {
+                label: island_label,
+                other: label,
+                line,
+                col,
+            });
+        }
+
+        let owner = *label_owners.entry(label).or_insert(island);
+        if owner != island && reported_duplicates.insert((island, label)) {
+            errors.push(Error::DuplicateLabel { label, line, col });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorList(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::island::labeler::FloodFill;
+
+    fn check(raw_map: &str) -> MapResult<()> {
+        verify(raw_map.parse()?, &FloodFill, &Connectivity::four())
+    }
+
+    #[test]
+    fn valid_map() {
+        assert_eq!(check("00 1\n 0 1\n2  1\n"), Ok(()));
+        assert_eq!(check("   \n"), Ok(()));
+    }
+
+    #[test]
+    fn mixed_labels() {
+        assert_eq!(
+            check("001\n0 2\n"),
+            Err(ErrorList(vec![
+                Error::MixedLabels {
+                    label: 0,
+                    other: 1,
+                    line: 1,
+                    col: 3
+                },
+                Error::MixedLabels {
+                    label: 0,
+                    other: 2,
+                    line: 2,
+                    col: 3
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn mixed_labels_reported_once() {
+        assert_eq!(
+            check("0110\n").unwrap_err(),
+            ErrorList(vec![Error::MixedLabels {
+                label: 0,
+                other: 1,
+                line: 1,
+                col: 2
+            }])
+        );
+    }
+
+    #[test]
+    fn duplicate_label() {
+        assert_eq!(
+            check("0 1\n  \n00 \n"),
+            Err(ErrorList(vec![Error::DuplicateLabel {
+                label: 0,
+                line: 3,
+                col: 1
+            }]))
+        );
+    }
+
+    #[test]
+    fn mixed_and_duplicate_label() {
+        assert_eq!(
+            check("1 01\n"),
+            Err(ErrorList(vec![
+                Error::MixedLabels {
+                    label: 0,
+                    other: 1,
+                    line: 1,
+                    col: 4
+                },
+                Error::DuplicateLabel {
+                    label: 1,
+                    line: 1,
+                    col: 4
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn unlabelled_earth() {
+        assert_eq!(
+            check("0#\n #\n"),
+            Err(ErrorList(vec![
+                Error::UnlabelledEarth { line: 1, col: 2 },
+                Error::UnlabelledEarth { line: 2, col: 2 },
+            ]))
+        );
+    }
+
+    #[test]
+    fn follows_connectivity() {
+        let map: Map = "0 \n 0\n".parse().unwrap();
+        assert_eq!(verify(map, &FloodFill, &Connectivity::eight()), Ok(()));
+
+        let map: Map = "0 \n 0\n".parse().unwrap();
+        assert!(verify(map, &FloodFill, &Connectivity::four()).is_err());
+    }
+}
@@ -33,19 +33,17 @@ impl<T> Stack<T> {
 
         *self = mem::take(next);
 
-        return Some(item);
+        Some(item)
     }
 
     pub fn peak(&self) -> Option<Item<T>> {
         match self {
             TailNode => None,
-            Node { item, .. } => {
-                return Some(item.clone());
-            }
+            Node { item, .. } => Some(item.clone()),
         }
     }
 
-    pub fn iter(&self) -> StackIter<T> {
+    pub fn iter(&self) -> StackIter<'_, T> {
         StackIter::new(self)
     }
 
@@ -64,6 +62,22 @@ impl<T> Stack<T> {
     }
 }
 
+// Drop the nodes one by one: the default recursive drop overflows the stack on long lists
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        if let Node { next, .. } = self {
+            let mut next = mem::take(next.as_mut());
+
+            while let Node {
+                next: node_next, ..
+            } = &mut next
+            {
+                next = mem::take(node_next.as_mut());
+            }
+        }
+    }
+}
+
 impl<T> Default for Stack<T> {
     fn default() -> Self {
         Stack::new()
@@ -124,6 +138,15 @@ mod tests {
         assert_eq!(vec![3, 2, 1], data);
     }
 
+    #[test]
+    fn drop_long_stack() {
+        let mut stack = Stack::new();
+
+        for i in 0..1_000_000 {
+            stack.push(i);
+        }
+    }
+
     #[test]
     fn two_iterator() {
         let mut stack = Stack::new();
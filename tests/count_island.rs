@@ -1,5 +1,13 @@
 use garm_playground::libs::island;
-use island::{run, AppResult, Error, ErrorList};
+use island::{
+    count_stream, json, label_map, lakes, read_netpbm, regions, run, run_at_level, run_stream,
+    run_with, stats, sweep, terrain, verify, write_pbm, write_ppm, Alphabet, AppResult,
+    BoundingBox, Connectivity, Csv, Error, ErrorList, FloodFill, Generator, Labeler, Map,
+    MapFormat, NetpbmEncoding, Options, Parallel, ParseOptions, Stats, StreamError, Terrain,
+    UnionFind, TERRAIN_NAMES,
+};
+use std::fs;
+use std::io::Cursor;
 
 #[cfg(test)]
 #[test]
@@ -15,7 +23,8 @@ fn map_single_char_one_island() -> AppResult<()> {
 #[test]
 fn map_single_char_several_island() -> AppResult<()> {
     let input_map = "   \n# # # # # # # # # # # # # # # # \n";
-    let output_map = "   \n0 1 2 3 4 5 6 7 8 9 a b c d e f \n";
+    // Note: the short first line is padded with sea
+    let output_map = "                                \n0 1 2 3 4 5 6 7 8 9 a b c d e f \n";
 
     assert_eq!(run(input_map)?, output_map);
 
@@ -86,9 +95,1083 @@ fn map_complex() -> AppResult<()> {
     Ok(())
 }
 
+mod large {
+    use super::*;
+
+    #[test]
+    fn single_block_island() -> AppResult<()> {
+        let size = 2000;
+        let input_map = ("#".repeat(size) + "\n").repeat(size);
+        let output_map = ("0".repeat(size) + "\n").repeat(size);
+
+        assert_eq!(run(&input_map)?, output_map);
+
+        Ok(())
+    }
+
+    // one land path zigzagging through the whole map: the worst case for a recursive fill
+    #[test]
+    fn single_snake_island() -> AppResult<()> {
+        let (width, height) = (2000, 1001);
+        let snake = |c: char| -> String {
+            (0..height)
+                .map(|y| match y % 4 {
+                    0 | 2 => c.to_string().repeat(width),
+                    1 => " ".repeat(width - 1) + &c.to_string(),
+                    _ => c.to_string() + &" ".repeat(width - 1),
+                })
+                .map(|line| line + "\n")
+                .collect()
+        };
+
+        assert_eq!(run(&snake('#'))?, snake('0'));
+
+        Ok(())
+    }
+}
+
+mod connectivity {
+    use super::*;
+
+    const DIAGONAL_MAP: &str = "\
+#   # 
+ # #  
+  #  #
+";
+
+    #[test]
+    fn four_neighbours_by_default() -> AppResult<()> {
+        let output_map = "\
+0   1 
+ 2 3  
+  4  5
+";
+        assert_eq!(run(DIAGONAL_MAP)?, output_map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn eight_neighbours() -> AppResult<()> {
+        let options = Options {
+            connectivity: Connectivity::eight(),
+            ..Options::default()
+        };
+        let output_map = "\
+0   0 
+ 0 0  
+  0  1
+";
+        assert_eq!(run_with(DIAGONAL_MAP, &options)?, output_map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_neighbours() -> AppResult<()> {
+        let options = Options {
+            connectivity: "3:1".parse().unwrap(),
+            ..Options::default()
+        };
+        let output_map = "\
+0   1 
+ 2 0  
+  3  4
+";
+        assert_eq!(run_with(DIAGONAL_MAP, &options)?, output_map);
+
+        Ok(())
+    }
+}
+
+mod encoders {
+    use super::*;
+
+    fn many_islands(count: usize) -> String {
+        "# ".repeat(count) + "\n"
+    }
+
+    #[test]
+    fn base36_keeps_unlabelled_overflow() -> AppResult<()> {
+        let output_map = run(&many_islands(40))?;
+
+        assert!(output_map.starts_with("0 1 2 "));
+        assert!(output_map.ends_with("y z # # # # \n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn base62() -> AppResult<()> {
+        let options = Options {
+            encoder: Box::new(Alphabet::BASE62),
+            ..Options::default()
+        };
+        let output_map = run_with(&many_islands(40), &options)?;
+
+        assert!(output_map.ends_with("y z A B C D \n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unicode_thousands_of_islands() -> AppResult<()> {
+        let count = 5000;
+        let options = Options {
+            encoder: Box::new(Alphabet::UNICODE),
+            ..Options::default()
+        };
+        let output_map = run_with(&many_islands(count), &options)?;
+
+        let labels: Vec<char> = output_map.chars().step_by(2).take(count).collect();
+        for (label, c) in labels.into_iter().enumerate() {
+            assert_eq!(Alphabet::UNICODE.label(c), Some(label));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv() -> AppResult<()> {
+        let options = Options {
+            encoder: Box::new(Csv),
+            ..Options::default()
+        };
+        let output_map = run_with(&many_islands(1000), &options)?;
+
+        assert!(output_map.starts_with("0,,1,,2,"));
+        assert!(output_map.ends_with(",998,,999,\n"));
+
+        Ok(())
+    }
+}
+
+mod statistics {
+    use super::*;
+
+    #[test]
+    fn complex_map() -> AppResult<()> {
+        let input_map = "\
+#######  
+#     #  
+# ##  # #
+#     #  
+#######  
+";
+        let Stats(islands) = stats(input_map, &Options::default())?;
+
+        assert_eq!(islands.len(), 3);
+        assert_eq!(
+            islands.iter().map(|i| i.area).collect::<Vec<usize>>(),
+            vec![20, 2, 1]
+        );
+        assert_eq!(
+            islands[0].bounding_box,
+            BoundingBox {
+                min: (0, 0),
+                max: (6, 4)
+            }
+        );
+        assert_eq!(islands[1].perimeter, 6);
+        assert_eq!(islands[2].centroid, (8.0, 2.0));
+        assert_eq!(
+            islands
+                .iter()
+                .map(|i| i.touches_border)
+                .collect::<Vec<bool>>(),
+            vec![true, false, true]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn follows_connectivity() -> AppResult<()> {
+        let options = Options {
+            connectivity: Connectivity::eight(),
+            ..Options::default()
+        };
+        let Stats(islands) = stats("# \n #\n", &options)?;
+
+        assert_eq!(islands.len(), 1);
+        assert_eq!(islands[0].area, 2);
+        assert_eq!(islands[0].perimeter, 8);
+
+        Ok(())
+    }
+}
+
+mod regions {
+    use super::*;
+
+    #[test]
+    fn terrain_map() -> AppResult<()> {
+        let raw_map = fs::read_to_string("misc/count_island/terrain_map.txt").unwrap();
+        let regions = regions(&raw_map, &Options::default())?;
+        let summary: Vec<(char, usize, usize)> = regions
+            .classes()
+            .iter()
+            .map(|class| (class.class, class.regions, class.area))
+            .collect();
+
+        assert_eq!(summary, vec![('.', 2, 112), ('T', 4, 32), ('~', 3, 48)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn earth_regions_are_islands() -> AppResult<()> {
+        for name in ["simple_map", "complex_map", "empty_map"] {
+            let raw_map = fs::read_to_string(format!("misc/count_island/{}.txt", name)).unwrap();
+
+            for connectivity in [Connectivity::four(), Connectivity::eight()] {
+                let options = Options {
+                    connectivity,
+                    ..Options::default()
+                };
+                let Stats(islands) = stats(&raw_map, &options)?;
+
+                assert_eq!(regions(&raw_map, &options)?.count(&'#'), islands.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn ragged_lines() -> AppResult<()> {
+        let regions = regions("TT\nT\n", &Options::default())?;
+
+        assert_eq!(regions.classes().len(), 1);
+        assert_eq!(regions.count(&' '), 0);
+        assert_eq!(regions.class(&'T').map(|class| class.area), Some(3));
+
+        Ok(())
+    }
+}
+
+mod sweep {
+    use super::*;
+
+    const HEIGHT_MAP: &str = include_str!("../misc/count_island/height_map.txt");
+
+    #[test]
+    fn height_map() -> AppResult<()> {
+        let sweep = sweep(HEIGHT_MAP, &Options::default())?;
+        let levels: Vec<(u8, usize, usize, usize)> = sweep
+            .levels()
+            .iter()
+            .map(|water| (water.level, water.islands, water.splits, water.sunk))
+            .collect();
+
+        assert_eq!(
+            levels,
+            vec![
+                (0, 2, 0, 0),
+                (1, 4, 1, 0),
+                (2, 4, 0, 0),
+                (3, 3, 0, 1),
+                (4, 1, 0, 2),
+                (5, 1, 0, 0),
+                (6, 0, 0, 1),
+            ]
+        );
+        assert_eq!(sweep.split_levels(), vec![1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn islands_at_level() -> AppResult<()> {
+        let expected = [
+            "                        ",
+            "                        ",
+            "  00        1      22   ",
+            "   0       111    2222  ",
+            "           11      22   ",
+            "       3                ",
+            "                        ",
+            "                        ",
+        ];
+
+        assert_eq!(
+            run_at_level(HEIGHT_MAP, 2, &Options::default())?,
+            expected.join("\n") + "\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn same_count_as_labeling() -> AppResult<()> {
+        for connectivity in [Connectivity::four(), Connectivity::eight()] {
+            let options = Options {
+                connectivity,
+                ..Options::default()
+            };
+
+            for water in sweep(HEIGHT_MAP, &options)?.levels() {
+                let raw_map = run_at_level(HEIGHT_MAP, water.level, &options)?;
+                let Stats(islands) = stats(&raw_map, &options)?;
+
+                assert_eq!(islands.len(), water.islands, "level {}", water.level);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+mod lakes {
+    use super::*;
+
+    const COMPLEX_MAP: &str = include_str!("../misc/count_island/complex_map.txt");
+
+    #[test]
+    fn complex_map_lakes() -> AppResult<()> {
+        let lakes = lakes(COMPLEX_MAP, &Options::default())?;
+        let islands: Vec<Option<usize>> = lakes.lakes().iter().map(|l| l.island).collect();
+
+        assert_eq!(islands, vec![Some(0)]);
+        assert!(lakes.is_ocean((50, 0)));
+        assert_eq!(lakes.lake_at((1, 1)), Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn holes_in_stats() -> AppResult<()> {
+        let Stats(islands) = stats(COMPLEX_MAP, &Options::default())?;
+        let holes: Vec<usize> = islands.iter().map(|i| i.holes).collect();
+
+        assert_eq!(holes, vec![1, 0, 0, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_lakes() -> AppResult<()> {
+        let options = Options {
+            lake_encoder: Some(Box::new(Alphabet::GREEK)),
+            ..Options::default()
+        };
+        let input_map = "\
+#####  
+#   #  
+# # # #
+#   #  
+#####  
+";
+        let output_map = "\
+00000  
+0ααα0  
+0α1α0 2
+0ααα0  
+00000  
+";
+        assert_eq!(run_with(input_map, &options)?, output_map);
+
+        Ok(())
+    }
+}
+
+mod labelers {
+    use super::*;
+
+    fn connectivities() -> [Connectivity; 3] {
+        [
+            Connectivity::four(),
+            Connectivity::eight(),
+            Connectivity::knight(),
+        ]
+    }
+
+    fn assert_same_labeling(raw_map: &str) {
+        for connectivity in connectivities() {
+            let flood_fill = Options {
+                connectivity: connectivity.clone(),
+                labeler: Box::new(FloodFill),
+                encoder: Box::new(Csv),
+                ..Options::default()
+            };
+            let union_find = Options {
+                connectivity: connectivity.clone(),
+                labeler: Box::new(UnionFind),
+                encoder: Box::new(Csv),
+                ..Options::default()
+            };
+
+            assert_eq!(
+                run_with(raw_map, &flood_fill),
+                run_with(raw_map, &union_find)
+            );
+
+            for threads in [2, 3, 16] {
+                let parallel = Options {
+                    connectivity: connectivity.clone(),
+                    labeler: Box::new(Parallel::new(threads)),
+                    encoder: Box::new(Csv),
+                    ..Options::default()
+                };
+
+                assert_eq!(
+                    run_with(raw_map, &flood_fill),
+                    run_with(raw_map, &parallel),
+                    "{} threads",
+                    threads
+                );
+            }
+
+            if let Ok(expected) = run_with(raw_map, &flood_fill) {
+                let mut output = vec![];
+                run_stream(Cursor::new(raw_map), &mut output, &flood_fill).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_maps() {
+        for entry in fs::read_dir("misc/count_island").unwrap() {
+            let raw_map = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert_same_labeling(&raw_map);
+        }
+    }
+
+    fn random_map(seed: u64, width: usize, height: usize, density: u64) -> String {
+        let terrain = Terrain::Uniform {
+            density: density as f64 / 100.0,
+        };
+        Generator::new(width, height)
+            .seed(seed)
+            .generate(&terrain)
+            .to_string()
+    }
+
+    #[test]
+    fn generated_maps() {
+        for seed in 1..=50 {
+            let density = 20 + seed % 60;
+            let raw_map = random_map(
+                seed,
+                1 + (seed as usize * 7) % 60,
+                1 + seed as usize % 40,
+                density,
+            );
+            assert_same_labeling(&raw_map);
+        }
+    }
+
+    #[test]
+    fn generated_ragged_maps() {
+        for seed in 1..=20 {
+            let raw_map: String = random_map(seed, 30, 30, 55)
+                .lines()
+                .enumerate()
+                .map(|(y, line)| line[..1 + (y * 7 + seed as usize) % 30].to_string() + "\n")
+                .collect();
+            assert_same_labeling(&raw_map);
+        }
+    }
+
+    #[test]
+    fn parallel_generated_terrains() {
+        for name in TERRAIN_NAMES {
+            let terrain = terrain(name).unwrap();
+            let map = Generator::new(160, 120).seed(7).generate(&terrain);
+
+            for connectivity in connectivities() {
+                let expected = FloodFill.label(map.clone(), &connectivity);
+
+                for threads in [1, 2, 3, 5, 8] {
+                    let labelled = Parallel::new(threads).label(map.clone(), &connectivity);
+                    assert!(labelled == expected, "{} with {} threads", name, threads);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_terrains() {
+        for name in TERRAIN_NAMES {
+            let terrain = terrain(name).unwrap();
+            for seed in 1..=5 {
+                let map = Generator::new(64, 48).seed(seed).generate(&terrain);
+                assert_same_labeling(&map.to_string());
+            }
+        }
+    }
+}
+
+mod stream {
+    use super::*;
+
+    #[test]
+    fn count_sample_maps() {
+        let count = |name: &str| {
+            let raw_map = fs::read_to_string(format!("misc/count_island/{}.txt", name)).unwrap();
+            count_stream(raw_map.as_bytes(), &Options::default()).unwrap()
+        };
+
+        assert_eq!(count("simple_map"), 3);
+        assert_eq!(count("complex_map"), 4);
+        assert_eq!(count("marked_map"), 6);
+        assert_eq!(count("empty_map"), 0);
+    }
+
+    #[test]
+    fn count_with_connectivity() {
+        let options = Options {
+            connectivity: Connectivity::eight(),
+            ..Options::default()
+        };
+
+        assert_eq!(count_stream("# #\n # \n".as_bytes(), &options).unwrap(), 1);
+    }
+
+    #[test]
+    fn invalid_map() {
+        let mut output = vec![];
+        let result = run_stream(Cursor::new("01[\n\n]23"), &mut output, &Options::default());
+
+        match result {
+            Err(StreamError::Map(errors)) => assert_eq!(Err(errors), run("01[\n\n]23")),
+            _ => panic!("invalid map not reported"),
+        }
+        assert!(output.is_empty());
+    }
+}
+
+mod map_format {
+    use super::*;
+
+    fn options(format: MapFormat) -> Options {
+        Options {
+            parse: ParseOptions::new().format(format),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn dots() -> AppResult<()> {
+        let raw_map = fs::read_to_string("misc/count_island/simple_map.txt").unwrap();
+        let dots_map = raw_map.replace(' ', ".").replace('#', "X");
+
+        let output = run_with(&dots_map, &options(MapFormat::DOTS))?;
+
+        assert_eq!(output, run(&raw_map)?.replace(' ', "."));
+        Ok(())
+    }
+
+    #[test]
+    fn binary() -> AppResult<()> {
+        // base36 labels would be mistaken for sea and earth
+        let options = Options {
+            encoder: Box::new(Alphabet::GREEK),
+            ..options(MapFormat::BINARY)
+        };
+        let output = run_with("0110\n1001\n", &options)?;
+
+        assert_eq!(output, "0αα0\nβ00γ\n");
+        Ok(())
+    }
+
+    #[test]
+    fn custom() -> AppResult<()> {
+        let format = MapFormat::new('~', '^', Alphabet::GREEK).unwrap();
+        let output = run_with("^~α\n~~^\n", &options(format))?;
+
+        assert_eq!(output, "0~1\n~~1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn default_chars_rejected() {
+        let result = run_with("# \n", &options(MapFormat::BINARY));
+
+        assert_eq!(
+            result,
+            Err(ErrorList(vec![
+                Error::InvalidChar {
+                    char: '#',
+                    line: 1,
+                    col: 1
+                },
+                Error::InvalidChar {
+                    char: ' ',
+                    line: 1,
+                    col: 2
+                },
+            ]))
+        );
+    }
+}
+
+mod netpbm {
+    use super::*;
+
+    fn assert_same_labeling(raw_map: &str) {
+        let map: Map = raw_map.parse().unwrap();
+
+        for connectivity in [Connectivity::four(), Connectivity::eight()] {
+            let options = Options {
+                connectivity,
+                encoder: Box::new(Csv),
+                ..Options::default()
+            };
+            let expected = run_with(raw_map, &options).unwrap();
+
+            for encoding in [NetpbmEncoding::Plain, NetpbmEncoding::Raw] {
+                let image = read_netpbm(&write_pbm(&map, encoding), None).unwrap();
+                let labelled = label_map(image, &options);
+
+                assert_eq!(labelled.render(options.encoder.as_ref()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_maps_round_trip() {
+        for name in ["simple_map", "complex_map", "marked_map"] {
+            let raw_map = fs::read_to_string(format!("misc/count_island/{}.txt", name)).unwrap();
+            assert_same_labeling(&raw_map);
+        }
+    }
+
+    #[test]
+    fn odd_widths_round_trip() {
+        // raw bitmap lines are padded to whole bytes
+        for width in 1..=17 {
+            let raw_map: String = (0..5)
+                .map(|y| {
+                    let line: String = (0..width)
+                        .map(|x| if (x * 3 + y * 5) % 7 < 3 { '#' } else { ' ' })
+                        .collect();
+                    line + "\n"
+                })
+                .collect();
+            assert_same_labeling(&raw_map);
+        }
+    }
+
+    #[test]
+    fn greymap_threshold() {
+        let image = b"P2\n4 2\n9\n9 9 0 4\n0 5 0 6\n";
+        let options = Options::default();
+
+        let map = label_map(read_netpbm(image, None).unwrap(), &options);
+        assert_eq!(map.to_string(), "00  \n 0 1\n");
+
+        let map = label_map(read_netpbm(image, Some(4)).unwrap(), &options);
+        assert_eq!(map.to_string(), "00 1\n 0 1\n");
+    }
+
+    #[test]
+    fn coloured_pixmap() -> AppResult<()> {
+        let map = label_map("# #\n".parse()?, &Options::default());
+        let pixmap = write_ppm(&map, NetpbmEncoding::Plain);
+
+        let [r0, g0, b0] = island::island_colour(0);
+        let [r1, g1, b1] = island::island_colour(1);
+        let expected = format!(
+            "P3\n3 1\n255\n{} {} {} 255 255 255 {} {} {}\n",
+            r0, g0, b0, r1, g1, b1
+        );
+        assert_eq!(String::from_utf8(pixmap).unwrap(), expected);
+        Ok(())
+    }
+}
+
+mod json {
+    use super::*;
+    use island::Json;
+
+    #[test]
+    fn report() -> AppResult<()> {
+        let report = json("0 #\n", &Options::default())?.to_string();
+
+        assert_eq!(
+            report,
+            concat!(
+                r#"{"width":3,"height":1,"count":2,"islands":["#,
+                r#"{"label":0,"mark":"0","area":1,"#,
+                r#""bounding_box":{"x":0,"y":0,"width":1,"height":1},"cells":[[0,0]]},"#,
+                r#"{"label":1,"mark":"1","area":1,"#,
+                r#""bounding_box":{"x":2,"y":0,"width":1,"height":1},"cells":[[2,0]]}"#,
+                r#"],"errors":[],"warnings":[]}"#
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn count_matches_stats() -> AppResult<()> {
+        let raw_map = fs::read_to_string("misc/count_island/complex_map.txt").unwrap();
+        let Stats(islands) = stats(&raw_map, &Options::default())?;
+        let report = json(&raw_map, &Options::default())?;
+
+        let Json::Object(members) = report else {
+            panic!("report is not an object")
+        };
+        assert_eq!(members[2], ("count", Json::Number(islands.len())));
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let errors = json("01[\n\n]23", &Options::default()).unwrap_err();
+
+        assert_eq!(
+            Json::from(&errors).to_string(),
+            concat!(
+                r#"{"width":null,"height":null,"count":null,"islands":[],"errors":["#,
+                r#"{"kind":"invalid_char","#,
+                r#""message":"invalid character '[' found at position 1:3","#,
+                r#""line":1,"col":3,"char":"["},"#,
+                r#"{"kind":"empty_line","message":"empty line found at line '2'","line":2},"#,
+                r#"{"kind":"invalid_char","#,
+                r#""message":"invalid character ']' found at position 3:1","#,
+                r#""line":3,"col":1,"char":"]"}"#,
+                r#"],"warnings":[]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn lenient_warnings() -> AppResult<()> {
+        let options = Options {
+            parse: ParseOptions::new().lenient(),
+            ..Options::default()
+        };
+        let report = json("#[\n", &options)?.to_string();
+
+        assert!(report.starts_with(r#"{"width":2,"height":1,"count":1,"#));
+        assert!(report.ends_with(concat!(
+            r#""errors":[],"warnings":[{"kind":"invalid_char","#,
+            r#""message":"invalid character '[' found at position 1:2","#,
+            r#""line":1,"col":2,"char":"["}]}"#
+        )));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_feature {
+    use super::*;
+    use island::Cell;
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sample_maps() {
+        for entry in fs::read_dir("misc/count_island").unwrap() {
+            let raw_map = fs::read_to_string(entry.unwrap().path()).unwrap();
+
+            match raw_map.parse::<Map>() {
+                Ok(map) => {
+                    let labelled = label_map(round_trip(&map), &Options::default());
+                    assert_eq!(round_trip(&map), map);
+                    assert_eq!(round_trip(&map).to_string(), map.to_string());
+                    assert_eq!(
+                        round_trip(&labelled).render(&Csv),
+                        run_with(
+                            &raw_map,
+                            &Options {
+                                encoder: Box::new(Csv),
+                                ..Options::default()
+                            }
+                        )
+                        .unwrap()
+                    );
+                }
+                Err(errors) => assert_eq!(round_trip(&errors), errors),
+            }
+        }
+    }
+
+    #[test]
+    fn cells() {
+        for cell in [Cell::Sea, Cell::Earth, Cell::MarkedEarth(4242)] {
+            assert_eq!(round_trip(&cell), cell);
+        }
+    }
+
+    #[test]
+    fn verify_errors() {
+        let raw_map = fs::read_to_string("misc/count_island/marked_map.txt").unwrap();
+        let errors = verify(&raw_map.replace('1', "2"), &Options::default()).unwrap_err();
+
+        assert_eq!(round_trip(&errors), errors);
+    }
+}
+
+mod ragged_lines {
+    use super::*;
+    use island::RaggedLines;
+
+    const RAGGED_MAP: &str = "#  \n#\n ##  \n";
+
+    #[test]
+    fn pad_by_default() -> AppResult<()> {
+        assert_eq!(run(RAGGED_MAP)?, "0    \n0    \n 11  \n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn trim() -> AppResult<()> {
+        let options = Options {
+            parse: ParseOptions::new().ragged_lines(RaggedLines::Trim),
+            ..Options::default()
+        };
+        assert_eq!(run_with(RAGGED_MAP, &options)?, "0  \n0  \n 11\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn strict() {
+        let options = Options {
+            parse: ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            ..Options::default()
+        };
+
+        assert_eq!(
+            run_with(RAGGED_MAP, &options),
+            Err(ErrorList(vec![
+                Error::RaggedLine {
+                    line: 2,
+                    expected: 3,
+                    found: 1
+                },
+                Error::RaggedLine {
+                    line: 3,
+                    expected: 3,
+                    found: 5
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn strict_rejects_sample_maps() {
+        let options = Options {
+            parse: ParseOptions::new().ragged_lines(RaggedLines::Strict),
+            ..Options::default()
+        };
+        let raw_map = fs::read_to_string("misc/count_island/simple_map.txt").unwrap();
+
+        assert!(run_with(&raw_map, &options).is_err());
+    }
+}
+
+mod parse_options {
+    use super::*;
+    use island::run_with_warnings;
+
+    const BROKEN_MAP: &str = "# #\n\n#!#\n";
+
+    fn options(parse: ParseOptions) -> Options {
+        Options {
+            parse,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn strict_by_default() {
+        assert_eq!(
+            run(BROKEN_MAP),
+            Err(ErrorList(vec![
+                Error::EmptyLine(2),
+                Error::InvalidChar {
+                    char: '!',
+                    line: 3,
+                    col: 2
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn lenient() -> AppResult<()> {
+        let options = options(ParseOptions::new().lenient());
+        let (raw_map, ErrorList(warnings)) = run_with_warnings(BROKEN_MAP, &options)?;
+
+        assert_eq!(raw_map, "0 1\n   \n2 3\n");
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(run_with(BROKEN_MAP, &options)?, raw_map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_warnings_in_strict_mode() -> AppResult<()> {
+        let (_, warnings) = run_with_warnings("# #\n", &Options::default())?;
+        assert_eq!(warnings, ErrorList(vec![]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fail_fast() {
+        let options = options(ParseOptions::new().lenient().fail_fast());
+
+        assert_eq!(
+            run_with(BROKEN_MAP, &options),
+            Err(ErrorList(vec![Error::EmptyLine(2)]))
+        );
+    }
+
+    #[test]
+    fn max_errors() {
+        let raw_map = "!\n".repeat(100);
+        let options = options(ParseOptions::new().max_errors(10));
+
+        assert_eq!(run_with(&raw_map, &options).unwrap_err().0.len(), 10);
+    }
+
+    #[test]
+    fn stream_fail_fast() {
+        let options = options(ParseOptions::new().fail_fast());
+        let result = count_stream(Cursor::new(BROKEN_MAP), &options);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            ErrorList(vec![Error::EmptyLine(2)]).to_string()
+        );
+    }
+}
+
+mod preserve_marks {
+    use super::*;
+
+    fn preserve() -> Options {
+        Options {
+            preserve_marks: true,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn marked_map() -> AppResult<()> {
+        let raw_map = fs::read_to_string("misc/count_island/marked_map.txt").unwrap();
+        let output_map = run_with(&raw_map, &preserve())?;
+        let lines: Vec<&str> = output_map.lines().collect();
+
+        assert_eq!(&lines[1][..35], "      00000        333 444 555  1  ");
+        assert_eq!(&lines[5][..16], "      22222     ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_land_joins_marked_island() -> AppResult<()> {
+        let input_map = "\
+aa#     
+  #  3  
+  ## # #
+";
+        let output_map = "\
+aaa     
+  a  3  
+  aa 3 b
+";
+        assert_eq!(run_with(input_map, &preserve())?, output_map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stable_on_second_run() -> AppResult<()> {
+        let input_map = "# 7 # #\n";
+        let first_run = run_with(input_map, &preserve())?;
+
+        assert_eq!(first_run, "8 7 9 a\n");
+        assert_eq!(run_with(&first_run, &preserve())?, first_run);
+
+        Ok(())
+    }
+}
+
+mod verify {
+    use super::*;
+
+    #[test]
+    fn labelled_output_is_valid() -> AppResult<()> {
+        for name in ["simple_map", "complex_map", "marked_map"] {
+            let raw_map = fs::read_to_string(format!("misc/count_island/{}.txt", name)).unwrap();
+            assert_eq!(verify(&run(&raw_map)?, &Options::default()), Ok(()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn marked_map() {
+        let raw_map = fs::read_to_string("misc/count_island/marked_map.txt").unwrap();
+        let ErrorList(errors) = verify(&raw_map, &Options::default()).unwrap_err();
+
+        assert_eq!(errors.len(), 3 * 9);
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, Error::UnlabelledEarth { line: 2..=4, .. })));
+    }
+
+    #[test]
+    fn hand_edited_map() {
+        let input_map = "\
+000 1 
+0 0 1 
+002 # 
+";
+        assert_eq!(
+            verify(input_map, &Options::default()),
+            Err(ErrorList(vec![
+                Error::MixedLabels {
+                    label: 0,
+                    other: 2,
+                    line: 3,
+                    col: 3
+                },
+                Error::UnlabelledEarth { line: 3, col: 5 },
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_errors_first() {
+        assert_eq!(
+            verify("0\n\n", &Options::default()),
+            Err(ErrorList(vec![Error::EmptyLine(2)]))
+        );
+    }
+}
+
 mod errors {
     use super::*;
 
+    #[test]
+    fn diagnostics() {
+        let raw_map = fs::read_to_string("misc/count_island/invalid_complex_map.txt").unwrap();
+        let errors = run(&raw_map).unwrap_err();
+        let output = island::Diagnostics::new(&raw_map).render(&errors);
+
+        assert!(output.contains(concat!(
+            "error: empty line found at line '7'\n",
+            "  --> 7:1\n",
+            "   |\n",
+            " 7 |\n",
+            "   | ^ empty line\n",
+        )));
+        assert!(output.ends_with("error: aborting due to 8 errors\n"));
+    }
+
     #[test]
     fn invalid_map_empty() {
         let result = run("");
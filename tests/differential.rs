@@ -0,0 +1,321 @@
+//! Differential testing of the labelers against a naive reference implementation
+//!
+//! Random maps are labelled by [`run_with`] and compared to [`reference_labels`] up to a renaming
+//! of the labels. A failing map is shrunk to a minimal failing case, printed with its seed.
+
+use garm_playground::libs::island;
+use island::{
+    count_stream, run_with, Connectivity, Csv, FloodFill, Labeler, Options, Random, UnionFind,
+};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// `true` for earth, `grid[y][x]`
+type Grid = Vec<Vec<bool>>;
+
+/// Label of every cell, `None` for sea
+type Labels = Vec<Vec<Option<usize>>>;
+
+fn raw_map(grid: &Grid) -> String {
+    grid.iter()
+        .map(|line| {
+            let line: String = line
+                .iter()
+                .map(|&earth| if earth { '#' } else { ' ' })
+                .collect();
+            line + "\n"
+        })
+        .collect()
+}
+
+fn random_grid(random: &mut Random, max_size: usize) -> Grid {
+    let (width, height) = (1 + random.below(max_size), 1 + random.below(max_size));
+    let density = random.next_f64();
+
+    (0..height)
+        .map(|_| (0..width).map(|_| random.chance(density)).collect())
+        .collect()
+}
+
+/*
+ * Reference
+ */
+
+/// Naive labeling: every earth cell starts with its own index as label and takes the smallest
+/// label of its neighbours until nothing changes
+fn reference_labels(grid: &Grid, connectivity: &Connectivity) -> Labels {
+    let width = grid[0].len();
+    let mut labels: Labels = grid
+        .iter()
+        .enumerate()
+        .map(|(y, line)| {
+            let cells = line.iter().enumerate();
+            cells
+                .map(|(x, &earth)| earth.then_some(y * width + x))
+                .collect()
+        })
+        .collect();
+
+    let neighbour = |x: usize, y: usize, (dx, dy): (isize, isize)| {
+        let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+        grid.get(y)?.get(x)?.then_some((x, y))
+    };
+
+    let offsets = connectivity.offsets();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for y in 0..grid.len() {
+            for x in 0..width {
+                let Some(label) = labels[y][x] else { continue };
+
+                for &offset in &offsets {
+                    if let Some((nx, ny)) = neighbour(x, y, offset) {
+                        let other = labels[ny][nx].expect("earth neighbour");
+                        if other < label {
+                            labels[y][x] = Some(other);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    labels
+}
+
+/// Labels of the output of [`run_with`] rendered with [`Csv`]
+fn parse_labels(output: &str) -> Labels {
+    output
+        .lines()
+        .map(|line| line.split(',').map(|label| label.parse().ok()).collect())
+        .collect()
+}
+
+/// `Ok` when both labelings are equal up to a renaming of the labels
+fn compare(expected: &Labels, actual: &Labels) -> Result<(), String> {
+    if expected.len() != actual.len() {
+        return Err(format!(
+            "{} lines instead of {}",
+            actual.len(),
+            expected.len()
+        ));
+    }
+
+    let mut renaming: HashMap<usize, usize> = HashMap::new();
+    let mut inverse: HashMap<usize, usize> = HashMap::new();
+
+    for (y, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        if expected.len() != actual.len() {
+            return Err(format!("line {} has {} cells", y + 1, actual.len()));
+        }
+
+        for (x, (&expected, &actual)) in expected.iter().zip(actual).enumerate() {
+            let consistent = match (expected, actual) {
+                (None, None) => true,
+                (Some(expected), Some(actual)) => {
+                    *renaming.entry(expected).or_insert(actual) == actual
+                        && *inverse.entry(actual).or_insert(expected) == expected
+                }
+                _ => false,
+            };
+
+            if !consistent {
+                return Err(format!("cell ({}, {}) is {:?}", x, y, actual));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare every labeler and the stream labeler to the reference on `grid`
+fn check(grid: &Grid, connectivity: &Connectivity) -> Result<(), String> {
+    let raw_map = raw_map(grid);
+    let expected = reference_labels(grid, connectivity);
+    let labelers: [(&str, Box<dyn Labeler>); 2] = [
+        ("flood-fill", Box::new(FloodFill)),
+        ("union-find", Box::new(UnionFind)),
+    ];
+
+    for (name, labeler) in labelers {
+        let options = Options {
+            encoder: Box::new(Csv),
+            connectivity: connectivity.clone(),
+            labeler,
+            ..Options::default()
+        };
+        let output = run_with(&raw_map, &options).map_err(|errors| errors.to_string())?;
+
+        compare(&expected, &parse_labels(&output))
+            .map_err(|error| format!("{}: {}", name, error))?;
+    }
+
+    let options = Options {
+        connectivity: connectivity.clone(),
+        ..Options::default()
+    };
+    let island_count = count_stream(Cursor::new(&raw_map), &options)
+        .map_err(|error| format!("stream: {}", error))?;
+    let mut labels: Vec<_> = expected.iter().flatten().flatten().collect();
+    labels.sort();
+    labels.dedup();
+
+    if island_count != labels.len() {
+        return Err(format!(
+            "stream: {} islands instead of {}",
+            island_count,
+            labels.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/*
+ * Shrinking
+ */
+
+/// Smaller variants of a grid: without a line, without a column, with an earth cell turned to sea
+fn shrink_candidates(grid: &Grid) -> Vec<Grid> {
+    let (width, height) = (grid[0].len(), grid.len());
+    let mut candidates = vec![];
+
+    if height > 1 {
+        for y in 0..height {
+            let mut candidate = grid.clone();
+            candidate.remove(y);
+            candidates.push(candidate);
+        }
+    }
+    if width > 1 {
+        for x in 0..width {
+            let mut candidate = grid.clone();
+            candidate.iter_mut().for_each(|line| {
+                line.remove(x);
+            });
+            candidates.push(candidate);
+        }
+    }
+    for y in 0..height {
+        for x in (0..width).filter(|&x| grid[y][x]) {
+            let mut candidate = grid.clone();
+            candidate[y][x] = false;
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+/// Shrink a failing grid as long as one of its [`shrink_candidates`] still fails
+fn shrink<F: Fn(&Grid) -> bool>(grid: Grid, fails: F) -> Grid {
+    let mut grid = grid;
+
+    while let Some(smaller) = shrink_candidates(&grid).into_iter().find(|g| fails(g)) {
+        grid = smaller;
+    }
+
+    grid
+}
+
+/// Panic with a minimal failing grid when `grid` fails
+fn assert_check(grid: Grid, connectivity: &Connectivity, seed: u64) {
+    if check(&grid, connectivity).is_err() {
+        let minimal = shrink(grid, |grid| check(grid, connectivity).is_err());
+        let error = check(&minimal, connectivity).unwrap_err();
+
+        panic!(
+            "seed {} with {:?} connectivity: {}\nminimal failing map:\n{}",
+            seed,
+            connectivity.offsets(),
+            error,
+            raw_map(&minimal).replace(' ', ".")
+        );
+    }
+}
+
+/*
+ * Tests
+ */
+
+fn connectivities() -> [Connectivity; 4] {
+    [
+        Connectivity::four(),
+        Connectivity::eight(),
+        Connectivity::knight(),
+        Connectivity::custom(&[(2, 0), (0, 1), (1, 1)]),
+    ]
+}
+
+#[test]
+fn random_maps() {
+    for seed in 0..300 {
+        let mut random = Random::new(seed);
+        let grid = random_grid(&mut random, 24);
+
+        for connectivity in connectivities() {
+            assert_check(grid.clone(), &connectivity, seed);
+        }
+    }
+}
+
+#[test]
+fn exhaustive_4x4() {
+    for connectivity in [Connectivity::four(), Connectivity::eight()] {
+        for bits in 0..1u64 << 16 {
+            let grid: Grid = (0..4)
+                .map(|y| (0..4).map(|x| bits >> (y * 4 + x) & 1 == 1).collect())
+                .collect();
+
+            assert_check(grid, &connectivity, bits);
+        }
+    }
+}
+
+#[test]
+fn reference() {
+    let grid: Grid = ["#  #", "## #", "   #", "#  #"]
+        .iter()
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+
+    assert_eq!(
+        reference_labels(&grid, &Connectivity::four()),
+        vec![
+            vec![Some(0), None, None, Some(3)],
+            vec![Some(0), Some(0), None, Some(3)],
+            vec![None, None, None, Some(3)],
+            vec![Some(12), None, None, Some(3)],
+        ]
+    );
+}
+
+#[test]
+fn compare_up_to_renaming() {
+    let labels =
+        |lines: &[&[Option<usize>]]| -> Labels { lines.iter().map(|line| line.to_vec()).collect() };
+    let expected = labels(&[&[Some(0), None, Some(1)]]);
+
+    assert!(compare(&expected, &labels(&[&[Some(5), None, Some(2)]])).is_ok());
+    assert!(compare(&expected, &labels(&[&[Some(1), None, Some(1)]])).is_err());
+    assert!(compare(&expected, &labels(&[&[Some(0), Some(2), Some(1)]])).is_err());
+    assert!(compare(&labels(&[&[Some(0), None, Some(0)]]), &expected).is_err());
+}
+
+#[test]
+fn shrink_to_minimal_case() {
+    // fails whenever two earth cells share a line
+    let fails = |grid: &Grid| {
+        grid.iter()
+            .any(|line| line.iter().filter(|&&e| e).count() > 1)
+    };
+    let mut random = Random::new(1);
+    let grid: Grid = (0..10)
+        .map(|_| (0..10).map(|_| random.chance(0.6)).collect())
+        .collect();
+
+    assert!(fails(&grid));
+    assert_eq!(shrink(grid, fails), vec![vec![true, true]]);
+}